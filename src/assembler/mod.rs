@@ -0,0 +1,340 @@
+use crate::runtime::Mode;
+use crate::runtime::storage::{START_SLOT, MEM_SIZE, XO_MEM_SIZE};
+
+use std::collections::HashMap;
+use std::fmt;
+
+// the same octo style mnemonics the disassembler prints, one statement per line (or after `then`).
+// `: name` defines a label, `:org addr` moves the output, `:const name value` defines a constant,
+// `db`/`dw` (or bare numbers) emit data and `#` starts a comment
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, PartialEq)]
+pub struct AssembleError {
+    pub line: usize, // 1 based
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(usize),
+    Name(String), // label or constant, looked up once every label is known
+}
+
+// a value to be masked into an emitted word once it's resolved
+struct Operand {
+    value: Value,
+    bits: usize,
+    shift: usize,
+}
+
+// one to two bytes of output, with at most one operand still to fill in
+struct Part {
+    base: usize,
+    operand: Option<Operand>,
+    size: usize,
+}
+
+struct Placed {
+    line: usize,
+    address: usize,
+    part: Part,
+}
+
+fn word(base: usize) -> Part {
+    return Part { base, operand: None, size: 2 };
+}
+
+fn word_with(base: usize, text: &str, bits: usize, shift: usize) -> Result<Part, String> {
+    let value = parse_value(text)?;
+    return Ok(Part { base, operand: Some(Operand { value, bits, shift }), size: 2 });
+}
+
+fn parse_number(text: &str) -> Option<usize> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        return usize::from_str_radix(hex, 16).ok();
+    }
+    if let Some(binary) = text.strip_prefix("0b") {
+        return usize::from_str_radix(binary, 2).ok();
+    }
+    return text.parse().ok();
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(number) = parse_number(text) {
+        return Ok(Value::Number(number));
+    }
+    let valid_name = text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid_name && parse_register(text).is_err() {
+        return Ok(Value::Name(text.to_string()));
+    }
+    return Err(format!("expected a number or name, got {}", text));
+}
+
+fn parse_register(text: &str) -> Result<usize, String> {
+    let register = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| usize::from_str_radix(digit, 16).ok());
+    return register.ok_or_else(|| format!("expected a register v0-vf, got {}", text));
+}
+
+fn is_register(text: &str) -> bool {
+    return parse_register(text).is_ok();
+}
+
+// schip instructions are also available in x0-chip, x0-chip ones only there
+fn require(mode: Mode, needed: Mode, name: &str) -> Result<(), String> {
+    let available = match needed {
+        Mode::CHIP8 => true,
+        Mode::SCHIP => mode != Mode::CHIP8,
+        Mode::X0CHIP => mode == Mode::X0CHIP,
+    };
+    if !available {
+        let needs = if needed == Mode::SCHIP { "schip or x0-chip" } else { "x0-chip" };
+        return Err(format!("{} needs {} mode", name, needs));
+    }
+    return Ok(());
+}
+
+// x in the second nibble, y in the third
+fn xy(base: usize, x: &str, y: &str) -> Result<Part, String> {
+    return Ok(word(base | parse_register(x)? << 8 | parse_register(y)? << 4));
+}
+
+fn x(base: usize, x: &str) -> Result<Part, String> {
+    return Ok(word(base | parse_register(x)? << 8));
+}
+
+fn data(values: &[&str], size: usize) -> Result<Vec<Part>, String> {
+    let mut parts: Vec<Part> = Vec::new();
+    for text in values {
+        let value = parse_value(text)?;
+        parts.push(Part { base: 0, operand: Some(Operand { value, bits: size * 8, shift: 0 }), size });
+    }
+    return Ok(parts);
+}
+
+// a single statement, `if ... then` carries the statement it guards on the same line
+fn statement(tokens: &[&str], mode: Mode) -> Result<Vec<Part>, String> {
+    if tokens.first().and_then(|token| parse_number(token)).is_some() {
+        return data(tokens, 1);
+    }
+
+    let part = match tokens {
+        ["db", values @ ..] => return data(values, 1),
+        ["dw", values @ ..] => return data(values, 2),
+
+        ["clear"] => word(0x00E0),
+        ["return"] => word(0x00EE),
+        ["scroll-down", n] => {
+            require(mode, Mode::SCHIP, "scroll-down")?;
+            word_with(0x00C0, n, 4, 0)?
+        },
+        ["scroll-up", n] => {
+            require(mode, Mode::X0CHIP, "scroll-up")?;
+            word_with(0x00D0, n, 4, 0)?
+        },
+        ["scroll-right"] => { require(mode, Mode::SCHIP, "scroll-right")?; word(0x00FB) },
+        ["scroll-left"] => { require(mode, Mode::SCHIP, "scroll-left")?; word(0x00FC) },
+        ["exit"] => { require(mode, Mode::SCHIP, "exit")?; word(0x00FD) },
+        ["lores"] => { require(mode, Mode::SCHIP, "lores")?; word(0x00FE) },
+        ["hires"] => { require(mode, Mode::SCHIP, "hires")?; word(0x00FF) },
+        ["jump", target] => word_with(0x1000, target, 12, 0)?,
+        [":call", target] => word_with(0x2000, target, 12, 0)?,
+        ["jump0", target] => word_with(0xB000, target, 12, 0)?,
+
+        ["if", register, "key", "then", rest @ ..] => return guarded(x(0xE0A1, register)?, rest, mode),
+        ["if", register, "-key", "then", rest @ ..] => return guarded(x(0xE09E, register)?, rest, mode),
+        ["if", left, comparison @ ("==" | "!="), right, "then", rest @ ..] => {
+            let equal = *comparison == "==";
+            // the opcodes skip, so `if` runs its statement on the opposite condition
+            let skip = if is_register(right) {
+                xy(if equal { 0x9000 } else { 0x5000 }, left, right)?
+            } else {
+                let mut skip = word_with(if equal { 0x4000 } else { 0x3000 }, right, 8, 0)?;
+                skip.base |= parse_register(left)? << 8;
+                skip
+            };
+            return guarded(skip, rest, mode);
+        },
+
+        ["save", first, "-", last] => { require(mode, Mode::X0CHIP, "save range")?; xy(0x5002, first, last)? },
+        ["load", first, "-", last] => { require(mode, Mode::X0CHIP, "load range")?; xy(0x5003, first, last)? },
+        ["save", register] => x(0xF055, register)?,
+        ["load", register] => x(0xF065, register)?,
+        ["saveflags", register] => { require(mode, Mode::SCHIP, "saveflags")?; x(0xF075, register)? },
+        ["loadflags", register] => { require(mode, Mode::SCHIP, "loadflags")?; x(0xF085, register)? },
+        ["bcd", register] => x(0xF033, register)?,
+        ["plane", mask] => { require(mode, Mode::X0CHIP, "plane")?; word_with(0xF001, mask, 2, 8)? },
+        ["audio"] => { require(mode, Mode::X0CHIP, "audio")?; word(0xF002) },
+        ["sprite", first, second, n] => {
+            let mut part = word_with(0xD000, n, 4, 0)?;
+            part.base |= parse_register(first)? << 8 | parse_register(second)? << 4;
+            part
+        },
+
+        ["i", ":=", "hex", register] => x(0xF029, register)?,
+        ["i", ":=", "bighex", register] => { require(mode, Mode::SCHIP, "bighex")?; x(0xF030, register)? },
+        ["i", ":=", "long", address] => {
+            require(mode, Mode::X0CHIP, "long")?;
+            return Ok(vec![word(0xF000), Part { base: 0, operand: Some(Operand { value: parse_value(address)?, bits: 16, shift: 0 }), size: 2 }]);
+        },
+        ["i", ":=", address] => word_with(0xA000, address, 12, 0)?,
+        ["i", "+=", register] => x(0xF01E, register)?,
+        ["delay", ":=", register] => x(0xF015, register)?,
+        ["buzzer", ":=", register] => x(0xF018, register)?,
+        ["pitch", ":=", register] => { require(mode, Mode::X0CHIP, "pitch")?; x(0xF03A, register)? },
+
+        [register, ":=", "random", mask] => {
+            let mut part = word_with(0xC000, mask, 8, 0)?;
+            part.base |= parse_register(register)? << 8;
+            part
+        },
+        [register, ":=", "delay"] => x(0xF007, register)?,
+        [register, ":=", "key"] => x(0xF00A, register)?,
+        [register, operator, source] if is_register(register) => {
+            let logic = match *operator {
+                ":=" => 0x0,
+                "|=" => 0x1,
+                "&=" => 0x2,
+                "^=" => 0x3,
+                "+=" => 0x4,
+                "-=" => 0x5,
+                ">>=" => 0x6,
+                "=-" => 0x7,
+                "<<=" => 0xE,
+                _ => return Err(format!("unknown operator {}", operator)),
+            };
+            if is_register(source) {
+                xy(0x8000 | logic, register, source)?
+            } else if *operator == ":=" || *operator == "+=" {
+                let mut part = word_with(if *operator == ":=" { 0x6000 } else { 0x7000 }, source, 8, 0)?;
+                part.base |= parse_register(register)? << 8;
+                part
+            } else {
+                return Err(format!("{} needs a register on the right", operator));
+            }
+        },
+        _ => return Err(format!("unknown statement: {}", tokens.join(" "))),
+    };
+    return Ok(vec![part]);
+}
+
+fn guarded(skip: Part, rest: &[&str], mode: Mode) -> Result<Vec<Part>, String> {
+    let mut parts = vec![skip];
+    if !rest.is_empty() {
+        parts.extend(statement(rest, mode)?);
+    }
+    return Ok(parts);
+}
+
+fn resolve(value: &Value, symbols: &HashMap<String, usize>) -> Result<usize, String> {
+    match value {
+        Value::Number(number) => Ok(*number),
+        Value::Name(name) => symbols.get(name).copied().ok_or_else(|| format!("undefined name {}", name)),
+    }
+}
+
+// assemble source into a rom that loads at 0x200, ready for `Storage::load_program`
+pub fn assemble(source: &str, mode: Mode) -> Result<Vec<u8>, AssembleError> {
+    let memory_size = if mode == Mode::X0CHIP { XO_MEM_SIZE } else { MEM_SIZE };
+    let mut symbols: HashMap<String, usize> = HashMap::new();
+    let mut placed: Vec<Placed> = Vec::new();
+    let mut address = START_SLOT;
+
+    // first pass: lay everything out and collect labels and constants
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| AssembleError { line, message };
+        let code = text.split('#').next().unwrap_or("");
+        let mut tokens: Vec<&str> = code.split_whitespace().collect();
+
+        // labels can share a line with the statement they mark
+        while tokens.first() == Some(&":") {
+            let name = tokens.get(1).ok_or_else(|| error(String::from("label needs a name")))?;
+            match parse_value(name).map_err(error)? {
+                Value::Name(name) => {
+                    if symbols.insert(name.clone(), address).is_some() {
+                        return Err(error(format!("{} is already defined", name)));
+                    }
+                },
+                Value::Number(_) => return Err(error(format!("bad label name {}", name))),
+            }
+            tokens.drain(0..2);
+        }
+
+        match tokens.as_slice() {
+            [] => {},
+            [":org", target] => {
+                let target = resolve(&parse_value(target).map_err(error)?, &symbols).map_err(error)?;
+                if target < START_SLOT || target >= memory_size {
+                    return Err(error(format!(":org {:#06x} is outside the program area", target)));
+                }
+                address = target;
+            },
+            [":const", name, value] => {
+                let value = resolve(&parse_value(value).map_err(error)?, &symbols).map_err(error)?;
+                match parse_value(name).map_err(error)? {
+                    Value::Name(name) => {
+                        if symbols.insert(name.clone(), value).is_some() {
+                            return Err(error(format!("{} is already defined", name)));
+                        }
+                    },
+                    Value::Number(_) => return Err(error(format!("bad constant name {}", name))),
+                }
+            },
+            [directive, ..] if directive.starts_with(':') && *directive != ":call" => {
+                return Err(error(format!("bad directive: {}", tokens.join(" "))));
+            },
+            _ => {
+                for part in statement(&tokens, mode).map_err(error)? {
+                    let size = part.size;
+                    placed.push(Placed { line, address, part });
+                    address += size;
+                }
+                if address > memory_size {
+                    return Err(error(String::from("program doesn't fit in memory")));
+                }
+            },
+        }
+    }
+
+    // second pass: fill in operands now every label is known
+    let end = placed.iter().map(|placed| placed.address + placed.part.size).max().unwrap_or(START_SLOT);
+    let mut rom: Vec<u8> = vec![0; end - START_SLOT];
+    let mut written: Vec<bool> = vec![false; end - START_SLOT];
+    for Placed { line, address, part } in placed.iter() {
+        let error = |message: String| AssembleError { line: *line, message };
+        let mut value = part.base;
+        if let Some(operand) = &part.operand {
+            let resolved = resolve(&operand.value, &symbols).map_err(error)?;
+            if resolved >= 1 << operand.bits {
+                return Err(error(format!("{:#x} doesn't fit in {} bits", resolved, operand.bits)));
+            }
+            value |= resolved << operand.shift;
+        }
+        let offset = address - START_SLOT;
+        if written[offset .. offset + part.size].iter().any(|&taken| taken) {
+            return Err(error(format!("output overlaps at {:#06x}", address)));
+        }
+        written[offset .. offset + part.size].iter_mut().for_each(|taken| *taken = true);
+        if part.size == 2 {
+            rom[offset] = (value >> 8) as u8;
+            rom[offset + 1] = value as u8;
+        } else {
+            rom[offset] = value as u8;
+        }
+    }
+    return Ok(rom);
+}
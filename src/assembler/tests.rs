@@ -0,0 +1,162 @@
+use crate::assembler::{assemble, AssembleError};
+use crate::disassembler::disassemble;
+use crate::runtime::Mode;
+
+// assemble a listing written the way the disassembler prints it and check it comes back the same
+fn round_trip(source: &str, mode: Mode) {
+    let rom = assemble(source, mode).unwrap_or_else(|error| panic!("{}", error));
+    let lines: Vec<String> = disassemble(&rom, mode).into_iter().map(|line| line.text).collect();
+    let expected: Vec<&str> = source.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    assert_eq!(lines, expected);
+}
+
+fn error(source: &str, mode: Mode) -> AssembleError {
+    return assemble(source, mode).expect_err("the source shouldn't assemble");
+}
+
+#[test]
+fn chip8_instructions_round_trip() {
+    round_trip("
+        clear
+        v0 := 0x05
+        v1 += 0x10
+        v2 := v3
+        v2 |= v3
+        v2 &= v3
+        v2 ^= v3
+        v2 += v3
+        v2 -= v3
+        v2 >>= v3
+        v2 =- v3
+        v2 <<= v3
+        if v4 != 0x12 then
+        v5 := 0x01
+        if v4 == 0x12 then
+        v5 := 0x02
+        if v4 != v5 then
+        v5 := 0x03
+        if v4 == v5 then
+        v5 := 0x04
+        if v6 -key then
+        v5 := 0x05
+        if v6 key then
+        v5 := 0x06
+        i := 0x300
+        va := random 0xff
+        sprite v1 v2 15
+        v8 := delay
+        v8 := key
+        delay := v8
+        buzzer := v8
+        i += v8
+        i := hex v8
+        bcd v8
+        save v9
+        load v9
+        :call 0x600
+        jump0 0x300
+    ", Mode::CHIP8);
+}
+
+#[test]
+fn schip_instructions_round_trip() {
+    round_trip("
+        hires
+        scroll-down 3
+        scroll-right
+        scroll-left
+        i := bighex v1
+        sprite v1 v2 0
+        saveflags v7
+        loadflags v7
+        lores
+        exit
+    ", Mode::SCHIP);
+}
+
+#[test]
+fn xo_chip_instructions_round_trip() {
+    round_trip("
+        scroll-up 2
+        save v1 - v4
+        load v4 - v1
+        plane 3
+        audio
+        pitch := v2
+        i := long 0xbeef
+        scroll-down 1
+        exit
+    ", Mode::X0CHIP);
+}
+
+#[test]
+fn a_long_load_can_be_guarded() {
+    let rom = assemble("if v0 == 1 then i := long 0x1234\nv1 := 2\n", Mode::X0CHIP).unwrap();
+    assert_eq!(rom, vec![0x40, 0x01, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x02]);
+    round_trip("
+        if v0 == 0x01 then
+        i := long 0x1234
+        v1 := 0x02
+    ", Mode::X0CHIP);
+}
+
+#[test]
+fn instructions_need_their_mode() {
+    let schip = [
+        ("hires", "hires"), ("lores", "lores"), ("exit", "exit"), ("scroll-down 1", "scroll-down"), ("scroll-left", "scroll-left"),
+        ("scroll-right", "scroll-right"), ("i := bighex v0", "bighex"), ("saveflags v0", "saveflags"), ("loadflags v0", "loadflags"),
+    ];
+    for (statement, name) in schip {
+        assert_eq!(error(statement, Mode::CHIP8).message, format!("{} needs schip or x0-chip mode", name));
+        assert!(assemble(statement, Mode::SCHIP).is_ok(), "{}", statement);
+    }
+    for statement in ["scroll-up 1", "save v0 - v1", "load v0 - v1", "plane 1", "audio", "pitch := v0", "i := long 0x200"] {
+        assert!(error(statement, Mode::SCHIP).message.ends_with("needs x0-chip mode"), "{}", statement);
+        assert!(assemble(statement, Mode::X0CHIP).is_ok(), "{}", statement);
+    }
+}
+
+#[test]
+fn labels_and_constants_resolve_forwards_and_back() {
+    let rom = assemble("
+        :const speed 3
+        : start
+        v0 := speed
+        jump later
+        : later jump start
+    ", Mode::CHIP8).unwrap();
+    assert_eq!(rom, vec![0x60, 0x03, 0x12, 0x04, 0x12, 0x00]);
+}
+
+#[test]
+fn bad_names_are_reported_on_their_line() {
+    assert_eq!(error("clear\njump nowhere", Mode::CHIP8), AssembleError { line: 2, message: String::from("undefined name nowhere") });
+    assert_eq!(error(": here\n: here", Mode::CHIP8).message, "here is already defined");
+    assert_eq!(error(":const here 1\n: here", Mode::CHIP8).message, "here is already defined");
+    assert_eq!(error(": 12", Mode::CHIP8).message, "bad label name 12");
+    assert_eq!(error(":const 12 1", Mode::CHIP8).message, "bad constant name 12");
+    assert_eq!(error(":const later 1\n:const speed soon", Mode::CHIP8).message, "undefined name soon");
+    assert_eq!(error(":bogus", Mode::CHIP8).message, "bad directive: :bogus");
+}
+
+#[test]
+fn org_moves_the_output_and_catches_overlaps() {
+    let rom = assemble("clear\n:org 0x206\nclear", Mode::CHIP8).unwrap();
+    assert_eq!(rom, vec![0x00, 0xE0, 0, 0, 0, 0, 0x00, 0xE0]);
+
+    assert_eq!(error("clear\nclear\n:org 0x202\nclear", Mode::CHIP8), AssembleError { line: 4, message: String::from("output overlaps at 0x0202") });
+    assert_eq!(error(":org 0x100", Mode::CHIP8).message, ":org 0x0100 is outside the program area");
+    assert_eq!(error(":org 0x1000", Mode::CHIP8).message, ":org 0x1000 is outside the program area");
+    assert!(assemble(":org 0x1000\nclear", Mode::X0CHIP).is_ok());
+}
+
+#[test]
+fn operands_have_to_fit() {
+    assert_eq!(error("v0 := 256", Mode::CHIP8).message, "0x100 doesn't fit in 8 bits");
+    assert_eq!(error("sprite v0 v1 16", Mode::CHIP8).message, "0x10 doesn't fit in 4 bits");
+    assert_eq!(error("jump 0x1000", Mode::CHIP8).message, "0x1000 doesn't fit in 12 bits");
+    assert_eq!(error("plane 4", Mode::X0CHIP).message, "0x4 doesn't fit in 2 bits");
+    assert_eq!(error("i := long 0x10000", Mode::X0CHIP).message, "0x10000 doesn't fit in 16 bits");
+    assert_eq!(error("db 0x100", Mode::CHIP8).message, "0x100 doesn't fit in 8 bits");
+    assert_eq!(error("v16 := 1", Mode::CHIP8).message, "unknown statement: v16 := 1");
+}
@@ -0,0 +1,392 @@
+use chip_8_interpreter::runtime::Mode;
+use chip_8_interpreter::runtime::timing::Timing;
+use chip_8_interpreter::runtime::random::{Random, RANDOM_NAMES};
+use chip_8_interpreter::runtime::quirks::{Quirks, PRESET_NAMES, QUIRK_NAMES};
+use chip_8_interpreter::frontend::headless::ScreenFormat;
+use chip_8_interpreter::frontend::keymap::{check_key_name, parse_hex_key};
+use chip_8_interpreter::frontend::gamepad::{GamepadMap, LAYOUT_NAMES};
+use chip_8_interpreter::frontend::video::{Fit, Flicker, FIT_NAMES, FLICKER_NAMES};
+use chip_8_interpreter::frontend::palette::{Palette, PALETTE_NAMES};
+use chip_8_interpreter::frontend::filter::{Filter, FILTER_NAMES};
+use chip_8_interpreter::frontend::sound::{Waveform, MAX_FREQUENCY, WAVEFORM_NAMES};
+use chip_8_interpreter::frontend::audio::AudioOutput;
+use chip_8_interpreter::frontend::pacer::{MIN_SPEED, MAX_SPEED};
+
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))] // the debugger only runs in the window
+pub struct Options {
+    pub file_name: String,
+    pub mode: Mode,
+    pub quirks: Quirks,
+    pub ips: Option<usize>,
+    pub speed: f64,
+    pub timing: Timing,
+    pub seed: Option<u64>,
+    pub random: Option<Random>,
+    pub config: Option<String>,
+    pub binds: Vec<(usize, Vec<String>)>,
+    pub gamepad: Option<GamepadMap>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub scale: Option<u32>,
+    pub fit: Option<Fit>,
+    pub fullscreen: bool,
+    pub palette: Option<Palette>,
+    pub flicker: Option<Flicker>,
+    pub filters: Option<Vec<Filter>>,
+    pub screenshot_scale: Option<usize>,
+    pub capture: Option<String>,
+    pub capture_audio: Option<String>,
+    pub frequency: Option<f32>,
+    pub waveform: Option<Waveform>,
+    pub volume: Option<f32>,
+    pub mute: bool,
+    pub audio: Option<AudioOutput>,
+    pub headless: bool,
+    pub cycles: Option<usize>,
+    pub frames: Option<usize>,
+    pub screen_format: Option<ScreenFormat>,
+    pub screen_output: Option<String>,
+    pub dump_output: Option<String>,
+    pub load_state: Option<String>,
+    pub save_state: Option<String>,
+    pub debug: bool,
+    pub breakpoints: Vec<usize>,
+    pub trace: bool,
+}
+
+const USAGE: &str = "usage: chip-8-interpreter path/to/file [mode] [--headless (--cycles N | --frames N | --replay path) [--format ascii|pbm|png] [--screen path] [--dump path] [--save-state path]] [--load-state path] [--debug] [--break addr]... [--trace] [--quirks preset] [--quirk name=on|off]... [--ips N] [--speed X] [--timing instructions|vip] [--seed N] [--random xorshift|vip|fixed[:n,n...]] [--config path] [--bind key=name[,name]]... [--gamepad layout] [--record path] [--replay path] [--scale N] [--fit integer|aspect] [--fullscreen] [--palette name|rrggbb,rrggbb[,rrggbb,rrggbb]] [--flicker draw|frame|blend|phosphor] [--filter name[,name]...] [--screenshot-scale N] [--capture file.gif|directory] [--capture-audio file.wav] [--tone HZ] [--waveform square|triangle|sine|sawtooth] [--volume 0-1] [--mute] [--audio sdl|null|file.wav]
+       chip-8-interpreter disasm path/to/file [mode]
+       chip-8-interpreter asm path/to/source [mode] [--output path]";
+
+fn bad_usage(message: String) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn parse_count(flag: &str, value: Option<String>) -> usize {
+    let value = value.unwrap_or_else(|| bad_usage(format!("{} needs a value", flag)));
+    return value.parse().unwrap_or_else(|_| bad_usage(format!("bad value for {}: {}", flag, value)));
+}
+
+fn parse_address(flag: &str, value: Option<String>) -> usize {
+    let value = value.unwrap_or_else(|| bad_usage(format!("{} needs a value", flag)));
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    return parsed.unwrap_or_else(|_| bad_usage(format!("bad address for {}: {}", flag, value)));
+}
+
+fn parse_mode(mode_arg: &str) -> Mode {
+    match mode_arg {
+        "0" => Mode::CHIP8,
+        "1" => Mode::SCHIP,
+        "2" => Mode::X0CHIP,
+        _ => bad_usage(format!("bad compatability mode selected: {}", mode_arg)),
+    }
+}
+
+// name=on|off for a single quirk
+fn parse_quirk(value: Option<String>) -> (String, bool) {
+    let value = value.unwrap_or_else(|| bad_usage(String::from("--quirk needs a value")));
+    let (name, setting) = value.split_once('=').unwrap_or_else(|| bad_usage(format!("--quirk takes name=on|off, got {}", value)));
+    let setting = match setting {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => bad_usage(format!("bad setting for quirk {}: {}", name, setting)),
+    };
+    if !QUIRK_NAMES.contains(&name) {
+        bad_usage(format!("unknown quirk {}, expected one of {}", name, QUIRK_NAMES.join(", ")));
+    }
+    return (name.to_string(), setting);
+}
+
+// key=name[,name] binds host keys to a hex key, replacing whatever the config had
+fn parse_bind(value: Option<String>) -> (usize, Vec<String>) {
+    let value = value.unwrap_or_else(|| bad_usage(String::from("--bind needs a value")));
+    let (key, names) = value.split_once('=').unwrap_or_else(|| bad_usage(format!("--bind takes key=name[,name], got {}", value)));
+    let key = parse_hex_key(key).unwrap_or_else(|| bad_usage(format!("{} isn't a hex key, expected 0-f", key)));
+    let names: Vec<String> = names.split(',').map(String::from).collect();
+    if let Some(error) = names.iter().find_map(|name| check_key_name(name).err()) {
+        bad_usage(error);
+    }
+    return (key, names);
+}
+
+// disasm path/to/file [mode], args still include the program name and subcommand
+pub fn parse_disasm(args: Vec<String>) -> (String, Mode) {
+    let positional: Vec<String> = args.into_iter().skip(2).collect();
+    if positional.is_empty() {
+        bad_usage(String::from("no program given"));
+    }
+    if let Some(arg) = positional.iter().find(|arg| arg.starts_with("--")) {
+        bad_usage(format!("unknown option: {}", arg));
+    }
+    let mode = parse_mode(positional.get(1).map(|s| s.as_str()).unwrap_or("0"));
+    return (positional[0].clone(), mode);
+}
+
+// asm path/to/source [mode] [--output path], the rom goes next to the source as .ch8 by default
+pub fn parse_asm(args: Vec<String>) -> (String, Mode, String) {
+    let mut positional: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+    let mut args = args.into_iter().skip(2);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)))),
+            _ if arg.starts_with("--") => bad_usage(format!("unknown option: {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+    if positional.is_empty() {
+        bad_usage(String::from("no source given"));
+    }
+    let mode = parse_mode(positional.get(1).map(|s| s.as_str()).unwrap_or("0"));
+    let source = positional[0].clone();
+    let output = output.unwrap_or_else(|| {
+        let stem = source.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&source);
+        format!("{}.ch8", stem)
+    });
+    return (source, mode, output);
+}
+
+pub fn parse(args: Vec<String>) -> Options {
+    let mut positional: Vec<String> = Vec::new();
+    let mut headless = false;
+    let mut cycles: Option<usize> = None;
+    let mut frames: Option<usize> = None;
+    let mut screen_format: Option<ScreenFormat> = None;
+    let mut screen_output: Option<String> = None;
+    let mut dump_output: Option<String> = None;
+    let mut load_state: Option<String> = None;
+    let mut save_state: Option<String> = None;
+    let mut debug = false;
+    let mut breakpoints: Vec<usize> = Vec::new();
+    let mut trace = false;
+    let mut preset: Option<Quirks> = None;
+    let mut quirk_overrides: Vec<(String, bool)> = Vec::new();
+    let mut ips: Option<usize> = None;
+    let mut speed: f64 = 1.0;
+    let mut timing = Timing::Instructions;
+    let mut seed: Option<u64> = None;
+    let mut random: Option<Random> = None;
+    let mut config: Option<String> = None;
+    let mut binds: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut gamepad: Option<GamepadMap> = None;
+    let mut record: Option<String> = None;
+    let mut replay: Option<String> = None;
+    let mut scale: Option<u32> = None;
+    let mut fit: Option<Fit> = None;
+    let mut fullscreen = false;
+    let mut palette: Option<Palette> = None;
+    let mut flicker: Option<Flicker> = None;
+    let mut filters: Option<Vec<Filter>> = None;
+    let mut screenshot_scale: Option<usize> = None;
+    let mut capture: Option<String> = None;
+    let mut capture_audio: Option<String> = None;
+    let mut frequency: Option<f32> = None;
+    let mut waveform: Option<Waveform> = None;
+    let mut volume: Option<f32> = None;
+    let mut mute = false;
+    let mut audio: Option<AudioOutput> = None;
+
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--cycles" => cycles = Some(parse_count(&arg, args.next())),
+            "--frames" => frames = Some(parse_count(&arg, args.next())),
+            "--format" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                screen_format = Some(ScreenFormat::from_name(&name).unwrap_or_else(|| bad_usage(format!("unknown screen format: {}", name))));
+            },
+            "--screen" => screen_output = args.next(),
+            "--dump" => dump_output = args.next(),
+            "--load-state" => load_state = args.next(),
+            "--save-state" => save_state = args.next(),
+            "--debug" => debug = true,
+            "--break" => breakpoints.push(parse_address(&arg, args.next())),
+            "--trace" => trace = true,
+            "--quirks" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                preset = Some(Quirks::preset(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown quirks preset {}, expected one of {}", name, PRESET_NAMES.join(", ")))
+                }));
+            },
+            "--quirk" => quirk_overrides.push(parse_quirk(args.next())),
+            "--ips" => {
+                let value = parse_count(&arg, args.next());
+                if value == 0 {
+                    bad_usage(String::from("--ips needs to be at least 1"));
+                }
+                ips = Some(value);
+            },
+            "--seed" => {
+                let value = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                seed = Some(value.parse().unwrap_or_else(|_| bad_usage(format!("bad value for --seed: {}", value))));
+            },
+            "--random" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                random = Some(Random::from_name(&name, 0).unwrap_or_else(|| {
+                    bad_usage(format!("unknown random number generator {}, expected one of {} (fixed can take a list, fixed:1,2,3)", name, RANDOM_NAMES.join(", ")))
+                }));
+            },
+            "--scale" => {
+                let value = parse_count(&arg, args.next());
+                if value == 0 || value > u16::MAX as usize {
+                    bad_usage(format!("bad value for --scale: {}", value));
+                }
+                scale = Some(value as u32);
+            },
+            "--fit" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                fit = Some(Fit::from_name(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown fit {}, expected one of {}", name, FIT_NAMES.join(", ")))
+                }));
+            },
+            "--fullscreen" => fullscreen = true,
+            "--screenshot-scale" => {
+                let value = parse_count(&arg, args.next());
+                if value == 0 || value > 100 {
+                    bad_usage(format!("bad value for --screenshot-scale: {}, expected 1 to 100", value));
+                }
+                screenshot_scale = Some(value);
+            },
+            "--tone" => {
+                let value = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                frequency = Some(value.parse().ok()
+                    .filter(|&frequency: &f64| frequency > 0.0 && frequency <= MAX_FREQUENCY)
+                    .unwrap_or_else(|| bad_usage(format!("bad value for --tone: {}, expected hz up to {}", value, MAX_FREQUENCY))) as f32);
+            },
+            "--waveform" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                waveform = Some(Waveform::from_name(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown waveform {}, expected one of {}", name, WAVEFORM_NAMES.join(", ")))
+                }));
+            },
+            "--volume" => {
+                let value = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                volume = Some(value.parse().ok()
+                    .filter(|volume: &f32| (0.0..=1.0).contains(volume))
+                    .unwrap_or_else(|| bad_usage(format!("bad value for --volume: {}, expected 0 to 1", value))));
+            },
+            "--mute" => mute = true,
+            "--audio" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                audio = Some(AudioOutput::from_name(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown audio output {}, expected sdl, null or a path ending in .wav", name))
+                }));
+            },
+            "--capture" => capture = args.next(),
+            "--capture-audio" => capture_audio = args.next(),
+            "--filter" => {
+                let chain = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                filters = Some(Filter::parse_chain(&chain).unwrap_or_else(|| {
+                    bad_usage(format!("bad filters {}, expected a list of {}", chain, FILTER_NAMES.join(", ")))
+                }));
+            },
+            "--flicker" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                flicker = Some(Flicker::from_name(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown flicker mode {}, expected one of {}", name, FLICKER_NAMES.join(", ")))
+                }));
+            },
+            "--palette" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                palette = Some(Palette::from_name(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown palette {}, expected one of {} or 2 or 4 hex colours", name, PALETTE_NAMES.join(", ")))
+                }));
+            },
+            "--record" => record = args.next(),
+            "--replay" => replay = args.next(),
+            "--config" => config = args.next(),
+            "--bind" => binds.push(parse_bind(args.next())),
+            "--gamepad" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                gamepad = Some(GamepadMap::layout(&name).unwrap_or_else(|| {
+                    bad_usage(format!("unknown gamepad layout {}, expected one of {}", name, LAYOUT_NAMES.join(", ")))
+                }));
+            },
+            "--timing" => {
+                let name = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                timing = Timing::from_name(&name).unwrap_or_else(|| bad_usage(format!("unknown timing: {}", name)));
+            },
+            "--speed" => {
+                let value = args.next().unwrap_or_else(|| bad_usage(format!("{} needs a value", arg)));
+                speed = value.parse().ok()
+                    .filter(|&speed: &f64| speed > 0.0 && speed.is_finite())
+                    .map(|speed: f64| speed.clamp(MIN_SPEED, MAX_SPEED))
+                    .unwrap_or_else(|| bad_usage(format!("bad value for --speed: {}", value)));
+            },
+            _ if arg.starts_with("--") => bad_usage(format!("unknown option: {}", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.is_empty() {
+        bad_usage(String::from("no program given"));
+    }
+    let mode = parse_mode(positional.get(1).map(|s| s.as_str()).unwrap_or("0"));
+    if headless && cycles.is_none() && frames.is_none() && replay.is_none() {
+        bad_usage(String::from("--headless needs --cycles, --frames or --replay"));
+    }
+    if replay.is_some() && (cycles.is_some() || record.is_some()) {
+        bad_usage(String::from("--replay goes a frame at a time and can't be combined with --cycles or --record"));
+    }
+    if headless && record.is_some() {
+        bad_usage(String::from("--record needs the window to take keys from"));
+    }
+    if headless && (capture.is_some() || capture_audio.is_some()) {
+        bad_usage(String::from("--capture and --capture-audio record the window, headless runs can use --screen"));
+    }
+    if headless && audio == Some(AudioOutput::Sdl) {
+        bad_usage(String::from("headless runs don't open a sound device, --audio can be null or a .wav file"));
+    }
+    // the mode picks the quirks unless a preset is given, then single quirks go on top
+    let mut quirks = preset.unwrap_or_else(|| Quirks::for_mode(mode));
+    for (name, setting) in quirk_overrides {
+        quirks.set(&name, setting);
+    }
+
+    return Options {
+        file_name: positional[0].clone(),
+        mode,
+        quirks,
+        ips,
+        speed,
+        timing,
+        seed,
+        random,
+        config,
+        binds,
+        gamepad,
+        record,
+        replay,
+        scale,
+        fit,
+        fullscreen,
+        palette,
+        flicker,
+        filters,
+        screenshot_scale,
+        capture,
+        capture_audio,
+        frequency,
+        waveform,
+        volume,
+        mute,
+        audio,
+        headless,
+        cycles,
+        frames,
+        screen_format,
+        screen_output,
+        dump_output,
+        load_state,
+        save_state,
+        debug,
+        breakpoints,
+        trace,
+    };
+}
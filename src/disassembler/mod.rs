@@ -0,0 +1,196 @@
+use crate::runtime::Mode;
+use crate::runtime::storage::{instruction::Instruction, START_SLOT};
+
+use std::collections::BTreeSet;
+
+const DATA_ROW_LENGTH: usize = 8;
+
+#[cfg(test)]
+mod tests;
+
+// one line of a listing, either a single instruction or a run of data bytes
+pub struct Line {
+    pub address: usize,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    pub is_code: bool,
+}
+
+// how control can leave an instruction, for the reachability pass
+enum Flow {
+    Next,
+    Jump(usize),
+    Call(usize),
+    Skip,
+    Stop, // return, exit, or a computed jump we can't follow
+}
+
+// octo style mnemonic for the instruction starting with opcode, None if it isn't one in this mode.
+// next is the word after it, only needed for the x0-chip long index load
+pub fn mnemonic(opcode: usize, next: Option<usize>, mode: Mode) -> Option<(String, usize)> {
+    return decode(opcode, next, mode).map(|(text, length, _)| (text, length));
+}
+
+fn decode(opcode: usize, next: Option<usize>, mode: Mode) -> Option<(String, usize, Flow)> {
+    let instruction = Instruction::decode(opcode);
+    let schip = mode != Mode::CHIP8;
+    let xo = mode == Mode::X0CHIP;
+    let (x, y, n, nn, nnn) = (instruction.x, instruction.y, instruction.n, instruction.nn, instruction.nnn);
+    let simple = |text: String| Some((text, 2, Flow::Next));
+
+    match instruction.identifier {
+        0x0 => match nnn {
+            0x0E0 => simple(String::from("clear")),
+            0x0EE => Some((String::from("return"), 2, Flow::Stop)),
+            0x0FB if schip => simple(String::from("scroll-right")),
+            0x0FC if schip => simple(String::from("scroll-left")),
+            0x0FD if schip => Some((String::from("exit"), 2, Flow::Stop)),
+            0x0FE if schip => simple(String::from("lores")),
+            0x0FF if schip => simple(String::from("hires")),
+            _ if schip && nnn & 0xFF0 == 0x0C0 => simple(format!("scroll-down {}", n)),
+            _ if xo && nnn & 0xFF0 == 0x0D0 => simple(format!("scroll-up {}", n)),
+            _ => None,
+        },
+        0x1 => Some((format!("jump {:#05x}", nnn), 2, Flow::Jump(nnn))),
+        0x2 => Some((format!(":call {:#05x}", nnn), 2, Flow::Call(nnn))),
+        0x3 => Some((format!("if v{:x} != {:#04x} then", x, nn), 2, Flow::Skip)),
+        0x4 => Some((format!("if v{:x} == {:#04x} then", x, nn), 2, Flow::Skip)),
+        0x5 => match n {
+            0x0 => Some((format!("if v{:x} != v{:x} then", x, y), 2, Flow::Skip)),
+            0x2 if xo => simple(format!("save v{:x} - v{:x}", x, y)),
+            0x3 if xo => simple(format!("load v{:x} - v{:x}", x, y)),
+            _ => None,
+        },
+        0x6 => simple(format!("v{:x} := {:#04x}", x, nn)),
+        0x7 => simple(format!("v{:x} += {:#04x}", x, nn)),
+        0x8 => {
+            let operator = match n {
+                0x0 => ":=",
+                0x1 => "|=",
+                0x2 => "&=",
+                0x3 => "^=",
+                0x4 => "+=",
+                0x5 => "-=",
+                0x6 => ">>=",
+                0x7 => "=-",
+                0xE => "<<=",
+                _ => return None,
+            };
+            simple(format!("v{:x} {} v{:x}", x, operator, y))
+        },
+        0x9 if n == 0 => Some((format!("if v{:x} == v{:x} then", x, y), 2, Flow::Skip)),
+        0xA => simple(format!("i := {:#05x}", nnn)),
+        0xB => Some((format!("jump0 {:#05x}", nnn), 2, Flow::Stop)),
+        0xC => simple(format!("v{:x} := random {:#04x}", x, nn)),
+        0xD => simple(format!("sprite v{:x} v{:x} {}", x, y, n)),
+        0xE => match nn {
+            0x9E => Some((format!("if v{:x} -key then", x), 2, Flow::Skip)),
+            0xA1 => Some((format!("if v{:x} key then", x), 2, Flow::Skip)),
+            _ => None,
+        },
+        0xF => match nn {
+            0x00 if xo && x == 0 => next.map(|address| (format!("i := long {:#06x}", address), 4, Flow::Next)),
+            0x01 if xo => simple(format!("plane {}", x)),
+            0x02 if xo && x == 0 => simple(String::from("audio")),
+            0x07 => simple(format!("v{:x} := delay", x)),
+            0x0A => simple(format!("v{:x} := key", x)),
+            0x15 => simple(format!("delay := v{:x}", x)),
+            0x18 => simple(format!("buzzer := v{:x}", x)),
+            0x1E => simple(format!("i += v{:x}", x)),
+            0x29 => simple(format!("i := hex v{:x}", x)),
+            0x30 if schip => simple(format!("i := bighex v{:x}", x)),
+            0x33 => simple(format!("bcd v{:x}", x)),
+            0x3A if xo => simple(format!("pitch := v{:x}", x)),
+            0x55 => simple(format!("save v{:x}", x)),
+            0x65 => simple(format!("load v{:x}", x)),
+            0x75 if schip => simple(format!("saveflags v{:x}", x)),
+            0x85 if schip => simple(format!("loadflags v{:x}", x)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn word_at(rom: &[u8], offset: usize) -> Option<usize> {
+    if offset + 1 >= rom.len() {
+        return None;
+    }
+    return Some(((rom[offset] as usize) << 8) | rom[offset + 1] as usize);
+}
+
+fn decode_at(rom: &[u8], address: usize, mode: Mode) -> Option<(String, usize, Flow)> {
+    let offset = address.checked_sub(START_SLOT)?;
+    let opcode = word_at(rom, offset)?;
+    return decode(opcode, word_at(rom, offset + 2), mode);
+}
+
+// follow every path from the entry point, returning the addresses instructions start at
+fn reachable(rom: &[u8], mode: Mode) -> BTreeSet<usize> {
+    let mut starts: BTreeSet<usize> = BTreeSet::new();
+    let mut pending: Vec<usize> = vec![START_SLOT];
+    while let Some(address) = pending.pop() {
+        if starts.contains(&address) {
+            continue;
+        }
+        let (_, length, flow) = match decode_at(rom, address, mode) {
+            Some(decoded) => decoded,
+            None => continue,
+        };
+        starts.insert(address);
+        let next = address + length;
+        match flow {
+            Flow::Next => pending.push(next),
+            Flow::Jump(target) => pending.push(target),
+            Flow::Call(target) => {
+                pending.push(target);
+                pending.push(next);
+            },
+            Flow::Skip => {
+                pending.push(next);
+                // the skipped instruction might be a four byte one
+                let skipped_length = decode_at(rom, next, mode).map(|(_, length, _)| length).unwrap_or(2);
+                pending.push(next + skipped_length);
+            },
+            Flow::Stop => {},
+        }
+    }
+    return starts;
+}
+
+// walk a rom loaded at 0x200, decoding what's reachable and leaving the rest as data
+pub fn disassemble(rom: &[u8], mode: Mode) -> Vec<Line> {
+    let starts = reachable(rom, mode);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut offset: usize = 0;
+    while offset < rom.len() {
+        let address = START_SLOT + offset;
+        if starts.contains(&address) {
+            let (text, length, _) = decode_at(rom, address, mode).expect("reachable instructions decode");
+            let length = length.min(rom.len() - offset);
+            lines.push(Line { address, bytes: rom[offset .. offset + length].to_vec(), text, is_code: true });
+            offset += length;
+            continue;
+        }
+
+        // gather data up to the next instruction or the end of the row
+        let mut end = offset + 1;
+        while end < rom.len() && end - offset < DATA_ROW_LENGTH && !starts.contains(&(START_SLOT + end)) {
+            end += 1;
+        }
+        let bytes = rom[offset .. end].to_vec();
+        let text = bytes.iter().map(|byte| format!("{:#04x}", byte)).collect::<Vec<String>>().join(" ");
+        lines.push(Line { address, bytes, text, is_code: false });
+        offset = end;
+    }
+    return lines;
+}
+
+// address, raw bytes and mnemonic, one line each
+pub fn format_listing(lines: &[Line]) -> String {
+    let mut listing = String::new();
+    for line in lines {
+        let bytes: String = line.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        listing.push_str(&format!("{:#06x}  {:<16}  {}\n", line.address, bytes, line.text));
+    }
+    return listing;
+}
@@ -0,0 +1,62 @@
+use crate::disassembler::{disassemble, reachable};
+use crate::runtime::Mode;
+
+fn rom(program: &[u16]) -> Vec<u8> {
+    return program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+}
+
+fn starts(rom: &[u8], mode: Mode) -> Vec<usize> {
+    return reachable(rom, mode).into_iter().collect();
+}
+
+// the text of each line, data rows included
+fn listing(rom: &[u8], mode: Mode) -> Vec<String> {
+    return disassemble(rom, mode).into_iter().map(|line| line.text).collect();
+}
+
+#[test]
+fn data_after_a_jump_is_bytes() {
+    let rom = rom(&[0x6001, 0x1200, 0xF0F0, 0x00E0]);
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x202]);
+    assert_eq!(listing(&rom, Mode::CHIP8), vec!["v0 := 0x01", "jump 0x200", "0xf0 0xf0 0x00 0xe0"]);
+}
+
+#[test]
+fn both_sides_of_a_skip_are_followed() {
+    // if v0 != 1 then jump 0x208, clear, and whatever the jump lands on
+    let rom = rom(&[0x3001, 0x1208, 0x00E0, 0x1204, 0x6102, 0x1208]);
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]);
+}
+
+#[test]
+fn a_skip_over_a_long_load_lands_after_all_four_bytes() {
+    // in xo-chip the skip goes over i := long as a whole, so the word after it never starts an instruction
+    let rom = rom(&[0x3001, 0xF000, 0x1234, 0x6102, 0x1208, 0x6203]);
+    assert_eq!(starts(&rom, Mode::X0CHIP), vec![0x200, 0x202, 0x206, 0x208]);
+    assert_eq!(listing(&rom, Mode::X0CHIP), vec!["if v0 != 0x01 then", "i := long 0x1234", "v1 := 0x02", "jump 0x208", "0x62 0x03"]);
+
+    // in chip-8 f000 isn't anything, so the skip's far side is the address word, read as jump 0x234
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x204]);
+}
+
+#[test]
+fn calls_are_followed_into_the_routine() {
+    let rom = rom(&[0x2206, 0x1202, 0xFFFF, 0x6005, 0x00EE]);
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x202, 0x206, 0x208]);
+    assert_eq!(listing(&rom, Mode::CHIP8), vec![":call 0x206", "jump 0x202", "0xff 0xff", "v0 := 0x05", "return"]);
+}
+
+#[test]
+fn jumps_into_the_middle_of_an_instruction_decode_the_same_every_time() {
+    // jump 0x201 lands on the second byte of v0 := 0x12 and reads 12 12 as jump 0x212, off the end of the rom.
+    // the instruction it overlaps comes first, so it's listed and the one inside it isn't
+    let rom = rom(&[0x6012, 0x1201]);
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x201, 0x202]);
+    assert_eq!(listing(&rom, Mode::CHIP8), vec!["v0 := 0x12", "jump 0x201"]);
+
+    // an odd start past the end of what came before is listed after the byte it leaves over
+    let rom = [0x60, 0x00, 0x12, 0x05, 0xAA, 0x00, 0xE0];
+    assert_eq!(starts(&rom, Mode::CHIP8), vec![0x200, 0x202, 0x205]);
+    assert_eq!(listing(&rom, Mode::CHIP8), vec!["v0 := 0x00", "jump 0x205", "0xaa", "clear"]);
+    assert_eq!(listing(&rom, Mode::CHIP8), listing(&rom, Mode::CHIP8));
+}
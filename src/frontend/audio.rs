@@ -0,0 +1,109 @@
+use crate::frontend::Audio;
+use crate::frontend::sound::{Pattern, SoundSettings, Tone};
+use crate::runtime::Runtime;
+use crate::runtime::clock::TIMER_HZ;
+
+use std::fs::File;
+use std::io::BufWriter;
+
+pub const WAV_SAMPLE_RATE: u32 = 44100;
+
+// where the buzzer goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioOutput {
+    Sdl, // the sound device, or nothing if there isn't one
+    Null, // nowhere
+    Wav(String), // a file, a frame of emulated time at a go
+}
+
+impl AudioOutput {
+    // "sdl", "null" or a path ending in .wav
+    pub fn from_name(name: &str) -> Option<AudioOutput> {
+        match name {
+            "sdl" => Some(AudioOutput::Sdl),
+            "null" | "none" => Some(AudioOutput::Null),
+            path if path.ends_with(".wav") => Some(AudioOutput::Wav(path.to_string())),
+            _ => None,
+        }
+    }
+}
+
+// hand the backend what the runtime sounds like over the frame that's ending, before the timers tick
+pub fn sound_frame(audio: &mut dyn Audio, runtime: &Runtime) {
+    audio.set_pattern(Pattern::from_runtime(runtime));
+    if runtime.is_beeping() {
+        audio.start_beep();
+    } else {
+        audio.stop_beep();
+    }
+}
+
+// swallows everything, for when there's no sound device or nobody wants to hear it
+pub struct NullAudio;
+
+impl Audio for NullAudio {
+    fn start_beep(&mut self) {}
+    fn stop_beep(&mut self) {}
+}
+
+// writes the buzzer to a wav as emulated time goes by rather than the wall clock's,
+// so the same run always comes out the same down to the sample
+pub struct WavAudio {
+    writer: Option<hound::WavWriter<BufWriter<File>>>, // gone once it's finished
+    tone: Tone,
+    frame_number: usize,
+}
+
+impl WavAudio {
+    pub fn create(path: &str, settings: &SoundSettings) -> Result<WavAudio, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: WAV_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(|error| format!("could not create {}: {}", path, error))?;
+        return Ok(WavAudio { writer: Some(writer), tone: Tone::new(settings.clone(), WAV_SAMPLE_RATE), frame_number: 0 });
+    }
+}
+
+impl Audio for WavAudio {
+    fn start_beep(&mut self) {
+        self.tone.on = true;
+    }
+    fn stop_beep(&mut self) {
+        self.tone.on = false;
+    }
+    fn set_sound(&mut self, settings: &SoundSettings) {
+        self.tone.settings = settings.clone();
+    }
+    fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        self.tone.pattern = pattern;
+    }
+
+    fn end_frame(&mut self) -> Result<(), String> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        // the samples this frame covers, worked out from the start so they add up to the sample rate
+        let frame_number = self.frame_number;
+        self.frame_number += 1;
+        let samples = (frame_number + 1) * WAV_SAMPLE_RATE as usize / TIMER_HZ - frame_number * WAV_SAMPLE_RATE as usize / TIMER_HZ;
+        let mut buffer: Vec<f32> = vec![0.0; samples];
+        self.tone.fill(&mut buffer);
+        let written: Result<(), hound::Error> = buffer.iter().try_for_each(|level| writer.write_sample((level * i16::MAX as f32) as i16));
+        if let Err(error) = written {
+            // once it's gone wrong there's no carrying on, so stop rather than saying so every frame
+            self.writer = None;
+            return Err(format!("could not write audio: {}", error));
+        }
+        return Ok(());
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if let Some(writer) = self.writer.take() {
+            writer.finalize().map_err(|error| format!("could not write audio: {}", error))?;
+        }
+        return Ok(());
+    }
+}
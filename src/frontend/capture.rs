@@ -0,0 +1,150 @@
+use crate::frontend::filter::Image;
+use crate::frontend::headless::encode_png;
+use crate::frontend::palette::Palette;
+use crate::frontend::Audio;
+use crate::frontend::audio::WavAudio;
+use crate::frontend::sound::{Pattern, SoundSettings};
+use crate::runtime::display::{Display, HIRES_WIDTH, HIRES_HEIGHT};
+use crate::runtime::clock::TIMER_HZ;
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SCREENSHOT_SCALE: usize = 10;
+const CENTISECONDS: usize = 100; // gif delays are in hundredths of a second
+
+// the screen at a scale, with the palette it's showing in
+pub fn screenshot(display: &Display, palette: &Palette, scale: usize) -> Vec<u8> {
+    return encode_png(&Image::from_display(display, palette).upscale(scale.max(1)));
+}
+
+// the first of name.0.png, name.1.png... that isn't there yet
+pub fn next_free_path(base: &str, extension: &str) -> String {
+    return (0..).map(|number| format!("{}.{}.{}", base, number, extension))
+        .find(|path| !Path::new(path).exists())
+        .expect("ran out of numbers");
+}
+
+// the machine's pixels as palette indices, blown up. lores is doubled so the size doesn't change when a game goes hires
+fn indexed_frame(display: &Display, scale: usize) -> (usize, usize, Vec<u8>) {
+    let factor = scale * (HIRES_WIDTH / display.width).max(1);
+    let (width, height) = (display.width * factor, display.height * factor);
+    let mut pixels: Vec<u8> = Vec::with_capacity(width * height);
+    for row in display.rows() {
+        let wide: Vec<u8> = row.iter().flat_map(|&pixel| std::iter::repeat_n(pixel & 0x03, factor)).collect();
+        for _i in 0..factor {
+            pixels.extend_from_slice(&wide);
+        }
+    }
+    return (width, height, pixels);
+}
+
+enum Frames {
+    // a frame is only written once the next different one turns up, so it knows how long it stayed up
+    Gif { encoder: gif::Encoder<BufWriter<File>>, pending: Option<(gif::Frame<'static>, usize)> },
+    Raw { directory: PathBuf, count: usize }, // numbered ppm files, for ffmpeg and the like
+}
+
+// a session written out a frame at a time at 60 frames a second, the beeper alongside as a wav
+pub struct Capture {
+    frames: Option<Frames>,
+    audio: Option<WavAudio>,
+    scale: usize,
+    frame_number: usize,
+}
+
+impl Capture {
+    // a .gif path records a gif, anything else is taken as a directory for raw frames
+    pub fn start(video: Option<&str>, audio: Option<&str>, scale: usize) -> Result<Capture, String> {
+        let frames = match video {
+            None => None,
+            Some(path) if path.ends_with(".gif") => {
+                let file = File::create(path).map_err(|error| format!("could not create {}: {}", path, error))?;
+                Some(Frames::Gif { encoder: start_gif(BufWriter::new(file), scale, path)?, pending: None })
+            },
+            Some(path) => {
+                fs::create_dir_all(path).map_err(|error| format!("could not create {}: {}", path, error))?;
+                Some(Frames::Raw { directory: PathBuf::from(path), count: 0 })
+            },
+        };
+        let audio = match audio {
+            None => None,
+            Some(path) => Some(WavAudio::create(path, &SoundSettings::default())?),
+        };
+        return Ok(Capture { frames, audio, scale: scale.max(1), frame_number: 0 });
+    }
+
+    // one 60hz frame: the screen as it ended up and whether the beeper was on through it, sounding like it does in the window
+    pub fn frame(
+        &mut self, display: &Display, palette: &Palette, sound: &SoundSettings, pattern: Option<Pattern>, beeping: bool,
+    ) -> Result<(), String> {
+        let frame_number = self.frame_number;
+        self.frame_number += 1;
+        match self.frames.as_mut() {
+            None => {},
+            Some(Frames::Gif { encoder, pending }) => {
+                let (width, height, pixels) = indexed_frame(display, self.scale);
+                let mut frame = gif::Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+                frame.palette = Some(palette.colors.concat());
+                let unchanged = pending.as_ref().is_some_and(|(last, _)| last.buffer == frame.buffer && last.palette == frame.palette);
+                if !unchanged {
+                    if let Some((last, started)) = pending.take() {
+                        write_gif_frame(encoder, last, started, frame_number)?;
+                    }
+                    *pending = Some((frame, frame_number));
+                }
+            },
+            Some(Frames::Raw { directory, count }) => {
+                let (width, height, pixels) = indexed_frame(display, self.scale);
+                let mut ppm: Vec<u8> = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+                pixels.iter().for_each(|&pixel| ppm.extend_from_slice(&palette.color(pixel)));
+                let path = directory.join(format!("frame_{:06}.ppm", count));
+                fs::write(&path, ppm).map_err(|error| format!("could not write {}: {}", path.display(), error))?;
+                *count += 1;
+            },
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_sound(sound);
+            audio.set_pattern(pattern);
+            if beeping {
+                audio.start_beep();
+            } else {
+                audio.stop_beep();
+            }
+            audio.end_frame()?;
+        }
+        return Ok(());
+    }
+
+    // write out whatever's held back and close the files
+    pub fn finish(mut self) -> Result<(), String> {
+        if let Some(Frames::Gif { mut encoder, pending }) = self.frames.take() {
+            if let Some((last, started)) = pending {
+                write_gif_frame(&mut encoder, last, started, self.frame_number.max(started + 1))?;
+            }
+            // taking the file back writes the end of the gif
+            let mut file = encoder.into_inner().map_err(|error| format!("could not write gif: {}", error))?;
+            file.flush().map_err(|error| format!("could not write gif: {}", error))?;
+        }
+        if let Some(mut audio) = self.audio.take() {
+            audio.finish()?;
+        }
+        return Ok(());
+    }
+}
+
+fn start_gif(file: BufWriter<File>, scale: usize, path: &str) -> Result<gif::Encoder<BufWriter<File>>, String> {
+    let (width, height) = (HIRES_WIDTH * scale, HIRES_HEIGHT * scale);
+    let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])
+        .map_err(|error| format!("could not write {}: {}", path, error))?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(|error| format!("could not write {}: {}", path, error))?;
+    return Ok(encoder);
+}
+
+// delays are rounded to whole centiseconds from the start of the capture, so they don't drift
+fn write_gif_frame(encoder: &mut gif::Encoder<BufWriter<File>>, mut frame: gif::Frame, started: usize, ended: usize) -> Result<(), String> {
+    let centiseconds = |frame: usize| frame * CENTISECONDS / TIMER_HZ;
+    frame.delay = (centiseconds(ended) - centiseconds(started)).max(1) as u16;
+    return encoder.write_frame(&frame).map_err(|error| format!("could not write gif: {}", error));
+}
@@ -0,0 +1,329 @@
+use crate::frontend::keymap::{KeyMap, check_key_name, parse_hex_key};
+use crate::frontend::gamepad::{GamepadMap, LAYOUT_NAMES};
+use crate::frontend::video::{Fit, Flicker, VideoSettings, FIT_NAMES, FLICKER_NAMES};
+use crate::frontend::palette::{Palette, PALETTE_NAMES};
+use crate::frontend::filter::{Filter, FILTER_NAMES};
+use crate::frontend::sound::{SoundSettings, Waveform, MAX_FREQUENCY, WAVEFORM_NAMES};
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+const SECTIONS: [&str; 4] = ["keys", "gamepad", "video", "audio"];
+
+// front end settings from a toml file. top level sections apply to every rom, and
+// [rom."name.ch8"] holds the same sections again for a single rom, overriding them:
+//
+//   [keys]
+//   5 = ["w", "up"]
+//   [rom."pong.ch8".keys]
+//   1 = "w"
+//   [gamepad]
+//   layout = "numpad"
+//   dead_zone = 0.2
+//   [gamepad.keys]
+//   5 = ["a", "rightx+"]
+//   [video]
+//   scale = 10
+//   fit = "integer"
+//   fullscreen = false
+//   palette = "amber"   (or colours, ["000000", "ffb000"])
+//   flicker = "phosphor"
+//   filters = ["scanlines", "bloom"]
+//   [audio]
+//   frequency = 440
+//   waveform = "triangle"
+//   volume = 0.5
+//   mute = false
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub keymap: KeyMap,
+    pub gamepad: GamepadMap,
+    pub video: VideoSettings,
+    pub sound: SoundSettings,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    Io(String),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(message) => write!(f, "{}", message),
+            ConfigError::Parse(message) => write!(f, "bad config: {}", message),
+            ConfigError::Invalid(message) => write!(f, "bad config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// $XDG_CONFIG_HOME/chip-8-interpreter/config.toml, falling back to ~/.config
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    return Some(base.join("chip-8-interpreter").join("config.toml"));
+}
+
+fn bind_keys(keymap: &mut KeyMap, keys: &Table) -> Result<(), ConfigError> {
+    for (key, value) in keys {
+        let hex_key = parse_hex_key(key)
+            .ok_or_else(|| ConfigError::Invalid(format!("{} isn't a hex key, expected 0-f", key)))?;
+        keymap.bind(hex_key, names(value, key)?);
+    }
+    return Ok(());
+}
+
+// the keyboard, where the hotkeys are off limits
+fn bind_keyboard(keymap: &mut KeyMap, keys: &Table) -> Result<(), ConfigError> {
+    for (key, value) in keys {
+        if let Some(error) = names(value, key)?.iter().find_map(|name| check_key_name(name).err()) {
+            return Err(ConfigError::Invalid(error));
+        }
+    }
+    return bind_keys(keymap, keys);
+}
+
+// a string or a list of strings
+fn names(value: &Value, key: &str) -> Result<Vec<String>, ConfigError> {
+    match value {
+        Value::String(name) => Ok(vec![name.clone()]),
+        Value::Array(values) => values.iter()
+            .map(|value| value.as_str().map(String::from)
+                .ok_or_else(|| ConfigError::Invalid(format!("key {} should be bound to key names", key))))
+            .collect(),
+        _ => Err(ConfigError::Invalid(format!("key {} should be bound to a key name or a list of them", key))),
+    }
+}
+
+// every setting in a table has to be one of these, so a misspelt one doesn't quietly do nothing
+fn check_names(table: &Table, known: &[&str], what: &str) -> Result<(), ConfigError> {
+    match table.keys().find(|name| !known.contains(&name.as_str())) {
+        Some(name) => Err(ConfigError::Invalid(format!("unknown {} {}, expected one of {}", what, name, known.join(", ")))),
+        None => Ok(()),
+    }
+}
+
+fn section<'a>(table: &'a Table, name: &str) -> Result<Option<&'a Table>, ConfigError> {
+    match table.get(name) {
+        None => Ok(None),
+        Some(Value::Table(section)) => Ok(Some(section)),
+        Some(_) => Err(ConfigError::Invalid(format!("{} should be a table", name))),
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path, rom: &str) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io(format!("could not read {}: {}", path.display(), error)))?;
+        return Config::parse(&text, rom);
+    }
+
+    // the settings for a rom, picked out by its file name
+    pub fn parse(text: &str, rom: &str) -> Result<Config, ConfigError> {
+        let table: Table = text.parse().map_err(|error: toml::de::Error| ConfigError::Parse(error.message().to_string()))?;
+        let mut config = Config::default();
+        check_names(&table, &[&SECTIONS[..], &["rom"]].concat(), "section")?;
+        config.apply(&table)?;
+
+        let rom_name = Path::new(rom).file_name().and_then(|name| name.to_str()).unwrap_or(rom);
+        if let Some(roms) = section(&table, "rom")? {
+            if let Some(overrides) = section(roms, rom_name)? {
+                check_names(overrides, &SECTIONS, "section")?;
+                config.apply(overrides)?;
+            }
+        }
+        return Ok(config);
+    }
+
+    fn apply(&mut self, table: &Table) -> Result<(), ConfigError> {
+        if let Some(keys) = section(table, "keys")? {
+            bind_keyboard(&mut self.keymap, keys)?;
+        }
+        if let Some(gamepad) = section(table, "gamepad")? {
+            self.apply_gamepad(gamepad)?;
+        }
+        if let Some(video) = section(table, "video")? {
+            self.apply_video(video)?;
+        }
+        if let Some(audio) = section(table, "audio")? {
+            self.apply_audio(audio)?;
+        }
+        return Ok(());
+    }
+
+    fn apply_audio(&mut self, table: &Table) -> Result<(), ConfigError> {
+        check_names(table, &["frequency", "waveform", "volume", "mute"], "audio setting")?;
+        let number = |value: &Value| value.as_float().or_else(|| value.as_integer().map(|value| value as f64));
+        if let Some(value) = table.get("frequency") {
+            self.sound.frequency = number(value).filter(|&frequency| frequency > 0.0 && frequency <= MAX_FREQUENCY)
+                .ok_or_else(|| ConfigError::Invalid(format!("audio frequency should be a number of hz up to {}", MAX_FREQUENCY)))? as f32;
+        }
+        if let Some(value) = table.get("waveform") {
+            self.sound.waveform = value.as_str().and_then(Waveform::from_name).ok_or_else(|| ConfigError::Invalid(
+                format!("audio waveform should be one of {}", WAVEFORM_NAMES.join(", "))))?;
+        }
+        if let Some(value) = table.get("volume") {
+            self.sound.volume = number(value).filter(|volume| (0.0..=1.0).contains(volume))
+                .ok_or_else(|| ConfigError::Invalid(String::from("audio volume should be a number from 0 to 1")))? as f32;
+        }
+        if let Some(value) = table.get("mute") {
+            self.sound.muted = value.as_bool()
+                .ok_or_else(|| ConfigError::Invalid(String::from("audio mute should be true or false")))?;
+        }
+        return Ok(());
+    }
+
+    fn apply_video(&mut self, table: &Table) -> Result<(), ConfigError> {
+        check_names(table, &["scale", "fit", "palette", "flicker", "filters", "fullscreen"], "video setting")?;
+        if let Some(value) = table.get("scale") {
+            let scale = value.as_integer().filter(|&scale| scale >= 1 && scale <= u16::MAX as i64)
+                .ok_or_else(|| ConfigError::Invalid(String::from("video scale should be a whole number from 1 up")))?;
+            self.video.scale = scale as u32;
+        }
+        if let Some(value) = table.get("fit") {
+            self.video.fit = value.as_str().and_then(Fit::from_name).ok_or_else(|| ConfigError::Invalid(
+                format!("video fit should be one of {}", FIT_NAMES.join(", "))))?;
+        }
+        if let Some(value) = table.get("palette") {
+            let invalid = || ConfigError::Invalid(format!(
+                "video palette should be one of {} or a list of 2 or 4 hex colours", PALETTE_NAMES.join(", ")));
+            self.video.palette = match value {
+                Value::String(name) => Palette::preset(name).ok_or_else(invalid)?,
+                Value::Array(_) => {
+                    let colors = names(value, "palette")?;
+                    Palette::from_hex(&colors.iter().map(String::as_str).collect::<Vec<&str>>()).ok_or_else(invalid)?
+                },
+                _ => return Err(invalid()),
+            };
+        }
+        if let Some(value) = table.get("flicker") {
+            self.video.flicker = value.as_str().and_then(Flicker::from_name).ok_or_else(|| ConfigError::Invalid(
+                format!("video flicker should be one of {}", FLICKER_NAMES.join(", "))))?;
+        }
+        if let Some(value) = table.get("filters") {
+            let invalid = || ConfigError::Invalid(format!("video filters should be a list of {}", FILTER_NAMES.join(", ")));
+            self.video.filters = match value {
+                Value::Array(_) => names(value, "filters")?.iter().map(|name| Filter::from_name(name)).collect::<Option<_>>()
+                    .ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+        }
+        if let Some(value) = table.get("fullscreen") {
+            self.video.fullscreen = value.as_bool()
+                .ok_or_else(|| ConfigError::Invalid(String::from("video fullscreen should be true or false")))?;
+        }
+        return Ok(());
+    }
+
+    // a layout starts the mapping over, then the dead zone and single keys change it
+    fn apply_gamepad(&mut self, table: &Table) -> Result<(), ConfigError> {
+        check_names(table, &["layout", "dead_zone", "keys"], "gamepad setting")?;
+        match table.get("layout") {
+            None => {},
+            Some(Value::String(name)) => {
+                let dead_zone = self.gamepad.dead_zone;
+                self.gamepad = GamepadMap::layout(name).ok_or_else(|| ConfigError::Invalid(
+                    format!("unknown gamepad layout {}, expected one of {}", name, LAYOUT_NAMES.join(", "))))?;
+                self.gamepad.dead_zone = dead_zone;
+            },
+            Some(_) => return Err(ConfigError::Invalid(String::from("gamepad layout should be a name"))),
+        }
+        match table.get("dead_zone") {
+            None => {},
+            Some(value) => {
+                let dead_zone = value.as_float().or_else(|| value.as_integer().map(|value| value as f64))
+                    .filter(|dead_zone| (0.0..1.0).contains(dead_zone))
+                    .ok_or_else(|| ConfigError::Invalid(String::from("gamepad dead_zone should be a number from 0 up to 1")))?;
+                self.gamepad.dead_zone = dead_zone as f32;
+            },
+        }
+        if let Some(keys) = section(table, "keys")? {
+            bind_keys(&mut self.gamepad.controls, keys)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::KEYBOARD_SIZE;
+
+    fn error(text: &str) -> String {
+        return Config::parse(text, "game.ch8").expect_err("the config shouldn't load").to_string();
+    }
+
+    #[test]
+    fn an_empty_config_is_the_defaults() {
+        let config = Config::parse("", "game.ch8").unwrap();
+        assert_eq!(config.keymap, KeyMap::default());
+        assert_eq!(config.video, VideoSettings::default());
+    }
+
+    #[test]
+    fn keys_take_a_name_or_a_list() {
+        let config = Config::parse("[keys]\n5 = [\"W\", \"up\"]\na = \"space\"", "game.ch8").unwrap();
+        assert_eq!(config.keymap.keys_for("w"), vec![0x5]);
+        assert_eq!(config.keymap.keys_for("up"), vec![0x5]);
+        assert_eq!(config.keymap.keys_for("space"), vec![0xA]);
+        assert_eq!(config.keymap.keys_for("q"), vec![0x4]);
+    }
+
+    #[test]
+    fn rom_overrides_replace_only_their_keys() {
+        let text = "
+            [keys]
+            5 = \"up\"
+            [rom.\"pong.ch8\".keys]
+            1 = [\"1\", \"w\"]
+            [rom.\"pong.ch8\".video]
+            scale = 7
+        ";
+        let pong = Config::parse(text, "roms/pong.ch8").unwrap();
+        assert_eq!(pong.keymap.bindings[0x1], vec!["1", "w"]);
+        assert_eq!(pong.keymap.bindings[0x5], vec!["up"]);
+        let defaults = KeyMap::default();
+        for key in (0..KEYBOARD_SIZE).filter(|&key| key != 0x1 && key != 0x5) {
+            assert_eq!(pong.keymap.bindings[key], defaults.bindings[key], "key {:x}", key);
+        }
+        assert_eq!(pong.video.scale, 7);
+
+        // another rom only gets the top level
+        let other = Config::parse(text, "tetris.ch8").unwrap();
+        assert_eq!(other.keymap.bindings[0x1], defaults.bindings[0x1]);
+        assert_eq!(other.keymap.bindings[0x5], vec!["up"]);
+        assert_eq!(other.video.scale, VideoSettings::default().scale);
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert_eq!(error("[keyz]\n5 = \"w\""), "bad config: unknown section keyz, expected one of keys, gamepad, video, audio, rom");
+        assert_eq!(error("[rom.\"game.ch8\".keyz]\n5 = \"w\""), "bad config: unknown section keyz, expected one of keys, gamepad, video, audio");
+        assert_eq!(error("[keys]\ng = \"w\""), "bad config: g isn't a hex key, expected 0-f");
+        assert_eq!(error("[keys]\n10 = \"w\""), "bad config: 10 isn't a hex key, expected 0-f");
+        assert!(error("[video]\nscael = 10").starts_with("bad config: unknown video setting scael"));
+        assert!(error("[audio]\nvolum = 1").starts_with("bad config: unknown audio setting volum"));
+        assert!(error("[gamepad]\nlayuot = \"numpad\"").starts_with("bad config: unknown gamepad setting layuot"));
+        assert!(error("[gamepad]\nlayout = \"joystick\"").starts_with("bad config: unknown gamepad layout joystick"));
+        assert_eq!(error("[keys]\n5 = [\"w\", \"PageUp\"]"), "bad config: PageUp is a hotkey and can't be bound");
+        assert_eq!(error("[rom.\"game.ch8\".keys]\n5 = \"f5\""), "bad config: f5 is a hotkey and can't be bound");
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        assert_eq!(error("[keys]\n5 = 3"), "bad config: key 5 should be bound to a key name or a list of them");
+        assert_eq!(error("[keys]\n5 = [\"w\", 3]"), "bad config: key 5 should be bound to key names");
+        assert_eq!(error("keys = 1"), "bad config: keys should be a table");
+        assert!(error("[video]\nscale = 0").contains("video scale"));
+        assert!(error("[video]\nflicker = \"sometimes\"").contains("video flicker"));
+        assert!(error("[audio]\nvolume = 2").contains("audio volume"));
+        assert!(error("[keys").starts_with("bad config:"));
+    }
+}
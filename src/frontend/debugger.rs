@@ -0,0 +1,305 @@
+use crate::disassembler::mnemonic;
+use crate::frontend::headless::register_dump;
+use crate::runtime::{Runtime, State};
+
+use std::collections::BTreeSet;
+use std::io::{stdin, stdout, BufRead, Write};
+
+const MEMORY_ROW_LENGTH: usize = 16;
+const DEFAULT_MEMORY_LENGTH: usize = 64;
+const HELP: &str = "\
+s, step [n]          run n instructions (default 1)
+n, next              step, running 2NNN calls through to their return
+c, continue          run until a breakpoint or watchpoint
+b, break <addr>      break when pc reaches addr
+d, delete <addr>     remove a breakpoint
+w, watch <target>    pause when a register (v0-vf, i, dt, st) or memory address changes
+unwatch <target>     remove a watchpoint
+l, list              show breakpoints and watchpoints
+r, regs              show registers, timers, stack and flags
+m, mem <addr> [len]  hex dump memory
+set <target> <value> write a register (v0-vf, i, pc, dt, st) or memory address
+trace on|off         print every instruction as it runs
+q, quit              exit the interpreter
+empty line repeats the last command";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Watch {
+    Variable(usize),
+    Index,
+    DelayTimer,
+    SoundTimer,
+    Memory(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RunMode {
+    Running,
+    Paused,
+    Stepping(usize), // instructions left before pausing again
+    SteppingOver { return_address: usize, stack_depth: usize },
+}
+
+pub struct Debugger {
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeSet<Watch>,
+    run_mode: RunMode,
+    watched_values: Vec<(Watch, usize)>, // taken before each step to compare against afterwards
+    last_command: String,
+    pub quit: bool, // quit was typed, the host should shut down properly rather than being cut off
+}
+
+fn parse_number(text: &str) -> Option<usize> {
+    if let Some(hex) = text.strip_prefix("0x") {
+        return usize::from_str_radix(hex, 16).ok();
+    }
+    return text.parse().ok();
+}
+
+fn parse_watch(text: &str) -> Option<Watch> {
+    match text {
+        "i" => Some(Watch::Index),
+        "dt" | "delay" => Some(Watch::DelayTimer),
+        "st" | "sound" => Some(Watch::SoundTimer),
+        _ => {
+            if let Some(register) = text.strip_prefix('v') {
+                return usize::from_str_radix(register, 16).ok()
+                    .filter(|&register| register < 16)
+                    .map(Watch::Variable);
+            }
+            return parse_number(text).map(Watch::Memory);
+        },
+    }
+}
+
+fn watch_value(runtime: &Runtime, watch: Watch) -> usize {
+    match watch {
+        Watch::Variable(register) => runtime.storage.variables[register],
+        Watch::Index => runtime.storage.index_register,
+        Watch::DelayTimer => runtime.delay_timer,
+        Watch::SoundTimer => runtime.sound_timer,
+        Watch::Memory(address) => runtime.storage.read(address).unwrap_or(0),
+    }
+}
+
+// the address, raw opcode and mnemonic at pc
+fn location(runtime: &Runtime) -> String {
+    let pc = runtime.storage.program_counter;
+    match runtime.storage.get_word(pc) {
+        Ok(opcode) => {
+            let next = runtime.storage.get_word(pc + 2).ok();
+            let text = mnemonic(opcode, next, runtime.mode).map(|(text, _)| text).unwrap_or_else(|| String::from("???"));
+            format!("{:#06x}: {:04X}  {}", pc, opcode, text)
+        },
+        Err(_) => format!("{:#06x}: out of bounds", pc),
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        return Debugger::new();
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        return Debugger {
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            run_mode: RunMode::Running,
+            watched_values: Vec::new(),
+            last_command: String::new(),
+            quit: false,
+        };
+    }
+
+    pub fn pause(&mut self) {
+        self.run_mode = RunMode::Paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return self.run_mode == RunMode::Paused;
+    }
+
+    // anything other than running freely until a breakpoint
+    pub fn is_active(&self) -> bool {
+        return self.run_mode != RunMode::Running;
+    }
+
+    // called before every instruction, drops into the repl when it's time to stop
+    pub fn before_step(&mut self, runtime: &mut Runtime) {
+        let pc = runtime.storage.program_counter;
+        let stop = match self.run_mode {
+            RunMode::Running => self.breakpoints.contains(&pc),
+            RunMode::Paused | RunMode::Stepping(0) => true,
+            RunMode::Stepping(_) => false,
+            RunMode::SteppingOver { return_address, stack_depth } => {
+                (pc == return_address && runtime.storage.stack_depth() == stack_depth)
+                    || self.breakpoints.contains(&pc)
+            },
+        };
+        if stop {
+            if self.breakpoints.contains(&pc) && self.run_mode != RunMode::Paused {
+                println!("breakpoint at {:#06x}", pc);
+            }
+            self.run_mode = RunMode::Paused;
+            self.repl(runtime);
+        }
+        if let RunMode::Stepping(remaining) = self.run_mode {
+            self.run_mode = RunMode::Stepping(remaining - 1);
+        }
+        self.watched_values = self.watchpoints.iter()
+            .map(|&watch| (watch, watch_value(runtime, watch)))
+            .collect();
+    }
+
+    // called after every instruction, pausing if anything being watched changed
+    pub fn after_step(&mut self, runtime: &Runtime) {
+        for &(watch, before) in self.watched_values.iter() {
+            let after = watch_value(runtime, watch);
+            if after != before {
+                println!("watchpoint {:?} changed from {:#04x} to {:#04x}", watch, before, after);
+                self.run_mode = RunMode::Paused;
+            }
+        }
+    }
+
+    // a halted machine never gets as far as before_step, so the host calls this instead. it stops here if the
+    // debugger was asked for or was stepping when the machine went down. everything but running works on it,
+    // and leaving the prompt goes back to waiting for f12
+    pub fn inspect(&mut self, runtime: &mut Runtime) {
+        if !self.is_active() {
+            return;
+        }
+        self.run_mode = RunMode::Paused;
+        match runtime.state {
+            State::Faulted(error) => println!("halted: {}", error),
+            State::Exited => println!("the program has exited"),
+            State::Running => {},
+        }
+        self.repl(runtime);
+        if runtime.is_halted() && !self.quit {
+            println!("the machine is halted, f12 comes back here");
+            self.run_mode = RunMode::Running;
+        }
+    }
+
+    fn repl(&mut self, runtime: &mut Runtime) {
+        println!("{}", location(runtime));
+        let input = stdin();
+        while self.run_mode == RunMode::Paused {
+            print!("(debug) ");
+            let _ = stdout().flush();
+            let mut line = String::new();
+            if input.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                // stdin closed, there's nobody left to drive the debugger
+                self.run_mode = RunMode::Running;
+                return;
+            }
+            let line = line.trim().to_string();
+            let line = if line.is_empty() { self.last_command.clone() } else { line };
+            self.last_command = line.clone();
+            self.execute(runtime, &line);
+        }
+    }
+
+    // run a single repl command against the paused machine
+    pub fn execute(&mut self, runtime: &mut Runtime, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let argument = |index: usize| words.get(index).copied();
+        match argument(0) {
+            None => {},
+            Some("s") | Some("step") => {
+                let count = argument(1).and_then(parse_number).unwrap_or(1).max(1);
+                self.run_mode = RunMode::Stepping(count);
+            },
+            Some("n") | Some("next") => {
+                let pc = runtime.storage.program_counter;
+                let is_call = runtime.storage.get_word(pc).map(|opcode| opcode & 0xF000 == 0x2000).unwrap_or(false);
+                self.run_mode = if is_call {
+                    RunMode::SteppingOver { return_address: pc + 2, stack_depth: runtime.storage.stack_depth() }
+                } else {
+                    RunMode::Stepping(1)
+                };
+            },
+            Some("c") | Some("continue") => self.run_mode = RunMode::Running,
+            Some("b") | Some("break") => match argument(1).and_then(parse_number) {
+                Some(address) => {
+                    self.breakpoints.insert(address);
+                },
+                None => println!("break needs an address"),
+            },
+            Some("d") | Some("delete") => match argument(1).and_then(parse_number) {
+                Some(address) => {
+                    self.breakpoints.remove(&address);
+                },
+                None => println!("delete needs an address"),
+            },
+            Some("w") | Some("watch") => match argument(1).and_then(parse_watch) {
+                Some(watch) => {
+                    self.watchpoints.insert(watch);
+                },
+                None => println!("watch needs a register or address"),
+            },
+            Some("unwatch") => match argument(1).and_then(parse_watch) {
+                Some(watch) => {
+                    self.watchpoints.remove(&watch);
+                },
+                None => println!("unwatch needs a register or address"),
+            },
+            Some("l") | Some("list") => {
+                let breakpoints: Vec<String> = self.breakpoints.iter().map(|address| format!("{:#06x}", address)).collect();
+                println!("breakpoints: {}", breakpoints.join(" "));
+                let watchpoints: Vec<String> = self.watchpoints.iter().map(|watch| format!("{:?}", watch)).collect();
+                println!("watchpoints: {}", watchpoints.join(" "));
+            },
+            Some("r") | Some("regs") => print!("{}", register_dump(runtime)),
+            Some("m") | Some("mem") => match argument(1).and_then(parse_number) {
+                Some(start) if start >= runtime.storage.memory.len() => {
+                    println!("{:#06x} is past the end of memory at {:#06x}", start, runtime.storage.memory.len() - 1);
+                },
+                Some(start) => {
+                    let length = argument(2).and_then(parse_number).unwrap_or(DEFAULT_MEMORY_LENGTH);
+                    let end = start.saturating_add(length).min(runtime.storage.memory.len());
+                    for row_start in (start..end).step_by(MEMORY_ROW_LENGTH) {
+                        let row_end = (row_start + MEMORY_ROW_LENGTH).min(end);
+                        let bytes: Vec<String> = runtime.storage.memory[row_start..row_end].iter()
+                            .map(|byte| format!("{:02x}", byte))
+                            .collect();
+                        println!("{:04x}: {}", row_start, bytes.join(" "));
+                    }
+                },
+                None => println!("mem needs an address"),
+            },
+            Some("set") => {
+                let value = argument(2).and_then(parse_number);
+                match (argument(1), value) {
+                    (Some("pc"), Some(value)) => {
+                        runtime.storage.program_counter = value;
+                        println!("{}", location(runtime));
+                    },
+                    (Some(target), Some(value)) => match parse_watch(target) {
+                        Some(Watch::Variable(register)) => runtime.storage.variables[register] = value & 0xFF,
+                        Some(Watch::Index) => runtime.storage.index_register = value,
+                        Some(Watch::DelayTimer) => runtime.delay_timer = value & 0xFF,
+                        Some(Watch::SoundTimer) => runtime.sound_timer = value & 0xFF,
+                        Some(Watch::Memory(address)) => {
+                            if runtime.storage.write(address, value & 0xFF).is_err() {
+                                println!("{:#06x} is out of bounds", address);
+                            }
+                        },
+                        None => println!("unknown target {}", target),
+                    },
+                    _ => println!("set needs a target and a value"),
+                }
+            },
+            Some("trace") => runtime.trace = argument(1) != Some("off"),
+            Some("q") | Some("quit") => {
+                self.quit = true;
+                self.run_mode = RunMode::Running;
+            },
+            Some("h") | Some("help") => println!("{}", HELP),
+            Some(command) => println!("unknown command {}, try help", command),
+        }
+    }
+}
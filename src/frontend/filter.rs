@@ -0,0 +1,165 @@
+use crate::frontend::palette::Palette;
+use crate::frontend::video::rgb_frame;
+use crate::runtime::display::Display;
+
+// filters want a few image pixels to each machine pixel to draw lines between them,
+// so the screen is blown up to about this wide first
+const FILTER_WIDTH: usize = 512;
+const SCANLINE_BRIGHTNESS: f32 = 0.45;
+const GRID_BRIGHTNESS: f32 = 0.3;
+const BLOOM_STRENGTH: f32 = 0.6;
+const CURVATURE: f32 = 0.08; // how far the corners are pulled in
+
+// an rgb picture, a row at a time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixel: usize, // image pixels to a machine pixel
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    // a frame as it comes from the display, one image pixel to a machine pixel
+    pub fn new(width: usize, height: usize, data: Vec<u8>) -> Image {
+        return Image { width, height, pixel: 1, data };
+    }
+
+    pub fn from_display(display: &Display, palette: &Palette) -> Image {
+        return Image::new(display.width, display.height, rgb_frame(display, palette));
+    }
+
+    // every pixel made into a factor by factor square
+    pub fn upscale(&self, factor: usize) -> Image {
+        let width = self.width * factor;
+        let mut data: Vec<u8> = Vec::with_capacity(self.data.len() * factor * factor);
+        for row in self.data.chunks(self.width * 3) {
+            let mut wide: Vec<u8> = Vec::with_capacity(width * 3);
+            for pixel in row.chunks(3) {
+                for _i in 0..factor {
+                    wide.extend_from_slice(pixel);
+                }
+            }
+            for _i in 0..factor {
+                data.extend_from_slice(&wide);
+            }
+        }
+        return Image { width, height: self.height * factor, pixel: self.pixel * factor, data };
+    }
+
+    fn scale_rows(&mut self, brightness: impl Fn(usize, usize) -> f32) {
+        let width = self.width;
+        for (index, pixel) in self.data.chunks_mut(3).enumerate() {
+            let factor = brightness(index % width, index / width);
+            pixel.iter_mut().for_each(|channel| *channel = (*channel as f32 * factor) as u8);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Scanlines, // darkens the bottom of each row of pixels, like the gaps between a crt's lines
+    Grid, // dark lines between every pixel, like an lcd
+    Bloom, // bright pixels glow into their neighbours
+    Curvature, // bows the picture out like the glass of a crt
+}
+
+pub const FILTER_NAMES: [&str; 4] = ["scanlines", "grid", "bloom", "curvature"];
+
+impl Filter {
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "scanlines" => Some(Filter::Scanlines),
+            "grid" => Some(Filter::Grid),
+            "bloom" => Some(Filter::Bloom),
+            "curvature" => Some(Filter::Curvature),
+            _ => None,
+        }
+    }
+
+    // "scanlines,bloom", applied in that order
+    pub fn parse_chain(text: &str) -> Option<Vec<Filter>> {
+        return text.split(',').map(|name| Filter::from_name(name.trim())).collect();
+    }
+
+    pub fn apply(&self, image: &mut Image) {
+        let pixel = image.pixel;
+        match self {
+            Filter::Scanlines => image.scale_rows(|_x, y| {
+                if y % pixel >= pixel - (pixel / 4).max(1) { SCANLINE_BRIGHTNESS } else { 1.0 }
+            }),
+            Filter::Grid => image.scale_rows(|x, y| {
+                if x % pixel == pixel - 1 || y % pixel == pixel - 1 { GRID_BRIGHTNESS } else { 1.0 }
+            }),
+            Filter::Bloom => bloom(image),
+            Filter::Curvature => curve(image),
+        }
+    }
+}
+
+// a box blur of one channel along a line of values, stride apart
+fn blur_line(values: &mut [f32], start: usize, length: usize, stride: usize, radius: usize) {
+    let line: Vec<f32> = (0..length).map(|i| values[start + i * stride]).collect();
+    let mut sum: f32 = line.iter().take(radius + 1).sum();
+    for i in 0..length {
+        let count = (i + radius + 1).min(length) - i.saturating_sub(radius);
+        values[start + i * stride] = sum / count as f32;
+        if i + radius + 1 < length {
+            sum += line[i + radius + 1];
+        }
+        if i >= radius {
+            sum -= line[i - radius];
+        }
+    }
+}
+
+fn bloom(image: &mut Image) {
+    let (width, height) = (image.width, image.height);
+    let radius = (image.pixel / 2).max(1);
+    let mut glow: Vec<f32> = image.data.iter().map(|&channel| channel as f32).collect();
+    for channel in 0..3 {
+        for y in 0..height {
+            blur_line(&mut glow, (y * width) * 3 + channel, width, 3, radius);
+        }
+        for x in 0..width {
+            blur_line(&mut glow, x * 3 + channel, height, width * 3, radius);
+        }
+    }
+    for (channel, glow) in image.data.iter_mut().zip(glow.iter()) {
+        *channel = (*channel as f32 + glow * BLOOM_STRENGTH).min(255.0) as u8;
+    }
+}
+
+// each pixel takes its colour from further out the further it is from the middle, anything off the edge is black
+fn curve(image: &mut Image) {
+    let (width, height) = (image.width, image.height);
+    let mut data: Vec<u8> = vec![0; image.data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let u = 2.0 * (x as f32 + 0.5) / width as f32 - 1.0;
+            let v = 2.0 * (y as f32 + 0.5) / height as f32 - 1.0;
+            let bend = 1.0 + CURVATURE * (u * u + v * v);
+            let source_x = (u * bend + 1.0) / 2.0 * width as f32;
+            let source_y = (v * bend + 1.0) / 2.0 * height as f32;
+            if source_x < 0.0 || source_y < 0.0 || source_x >= width as f32 || source_y >= height as f32 {
+                continue;
+            }
+            let from = (source_y as usize * width + source_x as usize) * 3;
+            let to = (y * width + x) * 3;
+            data[to..to + 3].copy_from_slice(&image.data[from..from + 3]);
+        }
+    }
+    image.data = data;
+}
+
+// blow the frame up and run it through the filters, or hand it back as it is when there aren't any
+pub fn apply_chain(filters: &[Filter], image: Image) -> Image {
+    if filters.is_empty() {
+        return image;
+    }
+    let mut image = image.upscale((FILTER_WIDTH / image.width).max(1));
+    for filter in filters {
+        filter.apply(&mut image);
+    }
+    return image;
+}
@@ -0,0 +1,64 @@
+use crate::frontend::keymap::KeyMap;
+
+pub const DEFAULT_DEAD_ZONE: f32 = 0.3; // of the way from the middle of a stick to the edge
+
+// which controller buttons and stick directions press each hex key. buttons go by sdl's controller names
+// ("a", "dpup", "leftshoulder"...), stick directions are an axis and a sign ("leftx-", "lefty+") and
+// triggers are just "triggerleft"/"triggerright"
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadMap {
+    pub controls: KeyMap,
+    pub dead_zone: f32,
+}
+
+pub const LAYOUT_NAMES: [&str; 3] = ["octo", "numpad", "paddles"];
+
+fn layout(bindings: &[(usize, &[&str])]) -> GamepadMap {
+    let mut controls = KeyMap { bindings: Default::default() };
+    for &(key, names) in bindings {
+        controls.bind(key, names.iter().map(|name| name.to_string()).collect());
+    }
+    return GamepadMap { controls, dead_zone: DEFAULT_DEAD_ZONE };
+}
+
+impl GamepadMap {
+    // octo era games steer with wasd (5 7 8 9) and act with e and q (6 and 4),
+    // older ones steer with 2 4 6 8 and fire with 5, and two player bat games use 1/4 and c/d
+    pub fn layout(name: &str) -> Option<GamepadMap> {
+        match name {
+            "octo" => Some(layout(&[
+                (0x5, &["dpup", "lefty-"]),
+                (0x7, &["dpleft", "leftx-"]),
+                (0x8, &["dpdown", "lefty+"]),
+                (0x9, &["dpright", "leftx+"]),
+                (0x6, &["a"]),
+                (0x4, &["b"]),
+                (0x1, &["x"]),
+                (0xE, &["y"]),
+                (0xF, &["start"]),
+            ])),
+            "numpad" => Some(layout(&[
+                (0x2, &["dpup", "lefty-"]),
+                (0x4, &["dpleft", "leftx-"]),
+                (0x8, &["dpdown", "lefty+"]),
+                (0x6, &["dpright", "leftx+"]),
+                (0x5, &["a"]),
+                (0x0, &["b"]),
+                (0xF, &["start"]),
+            ])),
+            "paddles" => Some(layout(&[
+                (0x1, &["dpup", "lefty-"]),
+                (0x4, &["dpdown", "lefty+"]),
+                (0xC, &["y", "righty-"]),
+                (0xD, &["a", "righty+"]),
+            ])),
+            _ => None,
+        }
+    }
+}
+
+impl Default for GamepadMap {
+    fn default() -> Self {
+        return GamepadMap::layout("octo").expect("the octo layout exists");
+    }
+}
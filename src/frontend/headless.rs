@@ -0,0 +1,156 @@
+use crate::frontend::Audio;
+use crate::frontend::audio::sound_frame;
+use crate::frontend::palette::Palette;
+use crate::frontend::filter::{Filter, Image, apply_chain};
+use crate::runtime::{Runtime, display::Display, error::ExecutionError};
+
+use std::fmt::Write;
+
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@']; // one per combination of the two planes
+const DUMP_ROW_LENGTH: usize = 16;
+
+pub enum ScreenFormat {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+impl ScreenFormat {
+    pub fn from_name(name: &str) -> Option<ScreenFormat> {
+        match name {
+            "ascii" | "txt" => Some(ScreenFormat::Ascii),
+            "pbm" => Some(ScreenFormat::Pbm),
+            "png" => Some(ScreenFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+// the end of a frame: the sound it made goes to the audio, then the timers tick
+fn end_frame(runtime: &mut Runtime, audio: &mut dyn Audio) {
+    sound_frame(audio, runtime);
+    runtime.tick_timers();
+    if let Err(error) = audio.end_frame() {
+        eprintln!("{}", error);
+    }
+}
+
+// end frames once a frame's worth has run, passing over frames with nothing to run in them
+fn finish_frames(runtime: &mut Runtime, audio: &mut dyn Audio) {
+    while !runtime.frame_running() && !runtime.is_halted() {
+        end_frame(runtime, audio);
+        runtime.start_frame();
+    }
+}
+
+// run a number of instructions, ending a frame whenever a frame's worth has run
+pub fn run_cycles(runtime: &mut Runtime, cycles: usize, audio: &mut dyn Audio) -> Result<(), ExecutionError> {
+    runtime.start_frame();
+    finish_frames(runtime, audio);
+    for _i in 0..cycles {
+        if runtime.is_halted() {
+            break;
+        }
+        runtime.step()?;
+        finish_frames(runtime, audio);
+    }
+    return Ok(());
+}
+
+// a frame's worth of instructions, then the sound and the timers
+pub fn run_frame(runtime: &mut Runtime, audio: &mut dyn Audio) -> Result<(), ExecutionError> {
+    runtime.start_frame();
+    while runtime.frame_running() {
+        runtime.step()?;
+    }
+    end_frame(runtime, audio);
+    return Ok(());
+}
+
+pub fn run_frames(runtime: &mut Runtime, frames: usize, audio: &mut dyn Audio) -> Result<(), ExecutionError> {
+    for _i in 0..frames {
+        if runtime.is_halted() {
+            break;
+        }
+        run_frame(runtime, audio)?;
+    }
+    return Ok(());
+}
+
+// the palette and filters only matter to png, the others are monochrome or spell the planes out
+pub fn render_screen(display: &Display, format: &ScreenFormat, palette: &Palette, filters: &[Filter]) -> Vec<u8> {
+    match format {
+        ScreenFormat::Ascii => screen_ascii(display).into_bytes(),
+        ScreenFormat::Pbm => screen_pbm(display).into_bytes(),
+        ScreenFormat::Png => encode_png(&apply_chain(filters, Image::from_display(display, palette))),
+    }
+}
+
+pub fn screen_ascii(display: &Display) -> String {
+    let mut screen = String::new();
+    for row in display.rows() {
+        for &pixel in row {
+            screen.push(ASCII_PIXELS[pixel as usize & 0x03]);
+        }
+        screen.push('\n');
+    }
+    return screen;
+}
+
+// plain pbm, a pixel is lit when it's set on any plane
+pub fn screen_pbm(display: &Display) -> String {
+    let mut screen = format!("P1\n{} {}\n", display.width, display.height);
+    for row in display.rows() {
+        let line: Vec<&str> = row.iter().map(|&pixel| if pixel != 0 { "1" } else { "0" }).collect();
+        screen.push_str(&line.join(" "));
+        screen.push('\n');
+    }
+    return screen;
+}
+
+pub fn encode_png(image: &Image) -> Vec<u8> {
+    let mut png_bytes: Vec<u8> = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_bytes, image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().expect("failed to write png header");
+        writer.write_image_data(&image.data).expect("failed to write png data");
+    }
+    return png_bytes;
+}
+
+// registers, timers, stack and a hex dump of memory
+pub fn state_dump(runtime: &Runtime) -> String {
+    let mut dump = register_dump(runtime);
+    let _ = writeln!(dump, "memory:");
+    for (row_index, row) in runtime.storage.memory.chunks(DUMP_ROW_LENGTH).enumerate() {
+        let bytes: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let _ = writeln!(dump, "{:04x}: {}", row_index * DUMP_ROW_LENGTH, bytes.join(" "));
+    }
+    return dump;
+}
+
+// everything but memory
+pub fn register_dump(runtime: &Runtime) -> String {
+    let storage = &runtime.storage;
+    let mut dump = String::new();
+    let _ = writeln!(dump, "state: {:?}", runtime.state);
+    let _ = writeln!(
+        dump,
+        "pc: {:#06x} i: {:#06x} delay: {} sound: {}",
+        storage.program_counter,
+        storage.index_register,
+        runtime.delay_timer,
+        runtime.sound_timer,
+    );
+    let variables: Vec<String> = storage.variables.iter().enumerate()
+        .map(|(i, value)| format!("v{:x}: {:#04x}", i, value))
+        .collect();
+    let _ = writeln!(dump, "{}", variables.join(" "));
+    let stack: Vec<String> = storage.stack.iter().map(|address| format!("{:#06x}", address)).collect();
+    let _ = writeln!(dump, "stack: {}", stack.join(" "));
+    let flags: Vec<String> = storage.flags.iter().map(|value| format!("{:#04x}", value)).collect();
+    let _ = writeln!(dump, "flags: {}", flags.join(" "));
+    return dump;
+}
@@ -0,0 +1,58 @@
+use crate::runtime::KEYBOARD_SIZE;
+
+// which host keys press each of the hex keys, by name ("x", "1", "up", "kp5"...).
+// the backend turns the names into whatever its keyboard speaks
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+    pub bindings: [Vec<String>; KEYBOARD_SIZE],
+}
+
+// the usual 1234/qwer/asdf/zxcv block, laid out like the cosmac vip keypad
+const QWERTY: [&str; KEYBOARD_SIZE] = ["x", "1", "2", "3", "q", "w", "e", "a", "s", "d", "z", "c", "4", "r", "f", "v"];
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        return KeyMap { bindings: QWERTY.map(|name| vec![name.to_string()]) };
+    }
+}
+
+// keys the front end keeps for its own hotkeys, they never reach the keypad so they can't be bound
+pub const HOTKEY_NAMES: [&str; 15] = [
+    "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11", "f12", "pageup", "pagedown", "home", "end",
+];
+
+// why a host key can't be bound, if it can't
+pub fn check_key_name(name: &str) -> Result<(), String> {
+    if HOTKEY_NAMES.contains(&name.to_lowercase().as_str()) {
+        return Err(format!("{} is a hotkey and can't be bound", name));
+    }
+    return Ok(());
+}
+
+// a hex key by its digit, 0-9 and a-f
+pub fn parse_hex_key(text: &str) -> Option<usize> {
+    if text.len() != 1 {
+        return None;
+    }
+    return usize::from_str_radix(text, 16).ok();
+}
+
+impl KeyMap {
+    // replace what a hex key is bound to
+    pub fn bind(&mut self, key: usize, names: Vec<String>) {
+        self.bindings[key] = names.into_iter().map(|name| name.to_lowercase()).collect();
+    }
+
+    // the hex keys a host key presses, usually one but nothing stops it doing more
+    pub fn keys_for(&self, name: &str) -> Vec<usize> {
+        return (0..KEYBOARD_SIZE).filter(|&key| self.bindings[key].iter().any(|bound| bound == name)).collect();
+    }
+
+    // every host key name that's bound to something
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.bindings.iter().flatten().map(|name| name.as_str()).collect();
+        names.sort();
+        names.dedup();
+        return names;
+    }
+}
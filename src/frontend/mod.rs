@@ -0,0 +1,381 @@
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod headless;
+pub mod debugger;
+pub mod pacer;
+pub mod keymap;
+pub mod gamepad;
+pub mod config;
+pub mod movie;
+pub mod video;
+pub mod palette;
+pub mod filter;
+pub mod capture;
+pub mod sound;
+pub mod audio;
+
+use crate::runtime::{Runtime, display::Display, error::ExecutionError};
+use debugger::Debugger;
+use pacer::Pacer;
+use movie::Movie;
+use palette::Palette;
+use video::Flicker;
+use sound::{step_frequency, Pattern, SoundSettings, VOLUME_STEP};
+use audio::sound_frame;
+use capture::{Capture, DEFAULT_SCREENSHOT_SCALE, next_free_path, screenshot};
+
+use std::path::Path;
+
+const SAVE_SLOTS: usize = 10;
+
+// shows the framebuffer somewhere
+pub trait Video {
+    fn present(&mut self, display: &Display);
+    // show the last frame again, say after the window's been resized. backends that blend frames
+    // together shouldn't take it as a new one
+    fn refresh(&mut self, display: &Display) {
+        self.present(display);
+    }
+    // somewhere to tell the user about halts and the like, if the backend has one
+    fn show_status(&mut self, _status: &str) {}
+    // between a window and the whole screen, for backends that have either
+    fn toggle_fullscreen(&mut self) {}
+    fn set_palette(&mut self, _palette: &Palette) {}
+}
+
+// makes the buzzer audible
+pub trait Audio {
+    fn start_beep(&mut self);
+    fn stop_beep(&mut self);
+    fn set_sound(&mut self, _settings: &SoundSettings) {}
+    // the xo-chip pattern to play instead of the tone, or none to go back to it
+    fn set_pattern(&mut self, _pattern: Option<Pattern>) {}
+    // a frame of emulated time has gone by. backends playing in real time go by the wall clock instead
+    fn end_frame(&mut self) -> Result<(), String> {
+        return Ok(());
+    }
+    // close anything the backend has open, it won't be used again
+    fn finish(&mut self) -> Result<(), String> {
+        return Ok(());
+    }
+}
+
+// things the user can ask of the front end itself, rather than of the running program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    SaveState,
+    LoadState,
+    NextSlot,
+    PreviousSlot,
+    Break, // drop into the debugger on the terminal
+    ToggleFullscreen,
+    NextPalette,
+    Screenshot,
+    ToggleCapture, // start or stop writing the session out as a gif and a wav
+    VolumeUp,
+    VolumeDown,
+    FrequencyUp,
+    FrequencyDown,
+    ToggleMute,
+    NextWaveform,
+    Redraw, // the window was resized or uncovered, show the screen again
+    Quit, // the window was closed
+}
+
+// something that happened on the input side, in the order it happened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Key { key: usize, pressed: bool }, // a hex key went down or up
+    Command(Command),
+}
+
+// reports what's happened since the last poll
+pub trait Input {
+    fn poll(&mut self) -> Vec<InputEvent>;
+}
+
+// a runtime wired up to the backends that show it, sound it and feed it keys
+pub struct Host {
+    pub runtime: Runtime,
+    video: Box<dyn Video>,
+    audio: Box<dyn Audio>,
+    input: Box<dyn Input>,
+    pub save_state_base: String, // quick save slots are written next to this path
+    slot: usize,
+    pub debugger: Debugger,
+    pub pacer: Pacer,
+    pub quit: bool, // the user asked to close the window, or quit from the debugger or with ctrl-c
+    frame_number: usize, // frames run so far, what movies count in
+    pub recording: Option<Movie>, // key changes get written down here as they happen
+    pub playback: Option<Movie>, // keys come from here rather than the input until it runs out
+    pub flicker: Flicker, // when to show the screen, the video backend does any blending
+    pub palettes: Vec<Palette>, // what the palette hotkey goes round, the first is showing to begin with
+    palette: usize,
+    pub screenshot_scale: usize, // also the scale captures are made at
+    capture: Option<Capture>,
+    pub sound: SoundSettings, // hand changes to set_sound so the backend hears about them
+}
+
+impl Host {
+    pub fn new(runtime: Runtime, video: Box<dyn Video>, audio: Box<dyn Audio>, input: Box<dyn Input>) -> Host {
+        return Host {
+            runtime,
+            video,
+            audio,
+            input,
+            save_state_base: String::from("chip8"),
+            slot: 0,
+            debugger: Debugger::new(),
+            pacer: Pacer::new(1.0),
+            quit: false,
+            frame_number: 0,
+            recording: None,
+            playback: None,
+            flicker: Flicker::Draw,
+            palettes: Palette::presets(),
+            palette: 0,
+            screenshot_scale: DEFAULT_SCREENSHOT_SCALE,
+            capture: None,
+            sound: SoundSettings::default(),
+        };
+    }
+
+    fn slot_path(&self) -> String {
+        return format!("{}.{}.state", self.save_state_base, self.slot);
+    }
+
+    fn report(&mut self, status: String) {
+        println!("{}", status);
+        self.video.show_status(&status);
+    }
+
+    pub fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::SaveState => {
+                let path = self.slot_path();
+                match self.runtime.save_state_file(&path) {
+                    Ok(()) => self.report(format!("saved slot {} to {}", self.slot, path)),
+                    Err(error) => self.report(format!("save failed: {}", error)),
+                }
+            },
+            Command::LoadState => {
+                let path = self.slot_path();
+                match self.runtime.load_state_file(&path) {
+                    Ok(()) => {
+                        self.report(format!("loaded slot {} from {}", self.slot, path));
+                        self.video.present(&self.runtime.display);
+                    },
+                    Err(error) => self.report(format!("load failed: {}", error)),
+                }
+            },
+            Command::NextSlot => {
+                self.slot = (self.slot + 1) % SAVE_SLOTS;
+                self.report(format!("slot {}", self.slot));
+            },
+            Command::PreviousSlot => {
+                self.slot = (self.slot + SAVE_SLOTS - 1) % SAVE_SLOTS;
+                self.report(format!("slot {}", self.slot));
+            },
+            Command::Break => {
+                self.report(String::from("paused, the debugger is on the terminal"));
+                self.debugger.pause();
+            },
+            Command::ToggleFullscreen => {
+                self.video.toggle_fullscreen();
+                self.video.refresh(&self.runtime.display);
+            },
+            Command::NextPalette => {
+                self.palette = (self.palette + 1) % self.palettes.len();
+                let palette = self.palettes[self.palette].clone();
+                self.video.set_palette(&palette);
+                self.video.present(&self.runtime.display);
+                self.report(format!("palette {}", palette.name));
+            },
+            Command::Screenshot => {
+                let path = next_free_path(&self.save_state_base, "png");
+                let png = screenshot(&self.runtime.display, &self.palettes[self.palette], self.screenshot_scale);
+                match std::fs::write(&path, png) {
+                    Ok(()) => self.report(format!("screenshot saved to {}", path)),
+                    Err(error) => self.report(format!("could not write {}: {}", path, error)),
+                }
+            },
+            Command::ToggleCapture => {
+                if self.capture.is_some() {
+                    self.stop_capture();
+                } else {
+                    let video = next_free_path(&self.save_state_base, "gif");
+                    let audio = Path::new(&video).with_extension("wav").to_string_lossy().into_owned();
+                    self.start_capture(Some(&video), Some(&audio));
+                }
+            },
+            Command::VolumeUp | Command::VolumeDown | Command::FrequencyUp | Command::FrequencyDown | Command::ToggleMute | Command::NextWaveform => {
+                let sound = &mut self.sound;
+                let status = match command {
+                    Command::VolumeUp | Command::VolumeDown => {
+                        let step = if command == Command::VolumeUp { VOLUME_STEP } else { -VOLUME_STEP };
+                        sound.volume = (sound.volume + step).clamp(0.0, 1.0);
+                        format!("volume {:.0}%", sound.volume * 100.0)
+                    },
+                    Command::FrequencyUp | Command::FrequencyDown => {
+                        sound.frequency = step_frequency(sound.frequency, command == Command::FrequencyUp);
+                        format!("tone {:.0} hz", sound.frequency)
+                    },
+                    Command::ToggleMute => {
+                        sound.muted = !sound.muted;
+                        String::from(if sound.muted { "muted" } else { "unmuted" })
+                    },
+                    _ => {
+                        sound.waveform = sound.waveform.next();
+                        format!("{} wave", sound.waveform.name())
+                    },
+                };
+                self.set_sound(self.sound.clone());
+                self.report(status);
+            },
+            Command::Redraw => self.video.refresh(&self.runtime.display),
+            Command::Quit => self.quit = true,
+        }
+    }
+
+    pub fn set_sound(&mut self, sound: SoundSettings) {
+        self.audio.set_sound(&sound);
+        self.sound = sound;
+    }
+
+    // write every frame from now on to a gif or a directory of frames, and the beeper to a wav
+    pub fn start_capture(&mut self, video: Option<&str>, audio: Option<&str>) {
+        self.stop_capture();
+        match Capture::start(video, audio, self.screenshot_scale) {
+            Ok(capture) => {
+                self.capture = Some(capture);
+                let paths: Vec<&str> = video.into_iter().chain(audio).collect();
+                self.report(format!("capturing to {}", paths.join(" and ")));
+            },
+            Err(error) => self.report(error),
+        }
+    }
+
+    // stop any capture and close the audio backend, before quitting
+    pub fn finish(&mut self) {
+        self.stop_capture();
+        if let Err(error) = self.audio.finish() {
+            self.report(error);
+        }
+    }
+
+    pub fn stop_capture(&mut self) {
+        if let Some(capture) = self.capture.take() {
+            match capture.finish() {
+                Ok(()) => self.report(String::from("capture finished")),
+                Err(error) => self.report(error),
+            }
+        }
+    }
+
+    // start writing down key changes, with the seed the random numbers come from
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Movie::new(&self.runtime));
+    }
+
+    // take the keys from a movie, from the frame it starts on
+    pub fn start_playback(&mut self, movie: Movie) {
+        self.runtime.reseed(movie.seed);
+        self.playback = Some(movie);
+    }
+
+    // one emulated frame: input, a clock's worth of instructions, then the timers and the screen
+    pub fn frame(&mut self) -> Result<(), ExecutionError> {
+        let frame = self.frame_number;
+        self.frame_number += 1;
+        self.runtime.clear_key_edges();
+        for event in self.input.poll() {
+            match event {
+                // the movie has the keys while it's playing
+                InputEvent::Key { .. } if self.playback.is_some() => {},
+                InputEvent::Key { key, pressed } => {
+                    self.runtime.set_key(key, pressed);
+                    if let Some(movie) = self.recording.as_mut() {
+                        movie.record(frame, key, pressed);
+                    }
+                },
+                InputEvent::Command(command) => self.handle_command(command),
+            }
+        }
+        if let Some(movie) = self.playback.take() {
+            for change in movie.changes_at(frame) {
+                self.runtime.set_key(change.key, change.pressed);
+            }
+            if frame + 1 < movie.frames {
+                self.playback = Some(movie);
+            } else {
+                self.report(format!("replay finished at frame {}", frame));
+            }
+        }
+        if let Some(movie) = self.recording.as_mut() {
+            movie.frames = self.frame_number;
+        }
+        if self.runtime.is_halted() {
+            self.debugger.inspect(&mut self.runtime);
+            if self.debugger.quit {
+                self.quit = true;
+                return Ok(());
+            }
+            if self.runtime.display.take_changed() {
+                self.video.present(&self.runtime.display);
+            }
+        }
+        self.runtime.start_frame();
+        while self.runtime.frame_running() {
+            self.debugger.before_step(&mut self.runtime);
+            if self.debugger.quit {
+                self.quit = true;
+                return Ok(());
+            }
+            let result = self.runtime.step();
+            self.debugger.after_step(&self.runtime);
+            // while stepping through in the debugger every change should show up straight away
+            let every_draw = self.debugger.is_active() || self.flicker == Flicker::Draw;
+            if every_draw && self.runtime.display.take_changed() {
+                self.video.present(&self.runtime.display);
+            }
+            if let Err(error) = result {
+                if self.runtime.display.take_changed() {
+                    self.video.present(&self.runtime.display);
+                }
+                self.audio.stop_beep();
+                self.video.show_status(&format!("halted: {}", error));
+                return Err(error);
+            }
+        }
+
+        let beeping = self.runtime.is_beeping();
+        let pattern = Pattern::from_runtime(&self.runtime);
+        sound_frame(self.audio.as_mut(), &self.runtime);
+        self.runtime.tick_timers();
+        if let Err(error) = self.audio.end_frame() {
+            self.report(error);
+        }
+        let changed = self.runtime.display.take_changed();
+        if changed || self.flicker.every_frame() {
+            self.video.present(&self.runtime.display);
+        }
+        if let Some(capture) = self.capture.as_mut() {
+            if let Err(error) = capture.frame(&self.runtime.display, &self.palettes[self.palette], &self.sound, pattern, beeping) {
+                self.capture = None;
+                self.report(format!("capture stopped: {}", error));
+            }
+        }
+        return Ok(());
+    }
+
+    // wait for the wall clock, then run however many frames are due
+    pub fn run_due_frames(&mut self) -> Result<(), ExecutionError> {
+        for _i in 0..self.pacer.wait() {
+            if self.quit {
+                break;
+            }
+            self.frame()?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::frontend::Audio;
+use crate::frontend::headless::run_frame;
+use crate::runtime::{Runtime, Mode, KEYBOARD_SIZE, error::ExecutionError};
+use crate::runtime::quirks::{Quirks, QUIRK_NAMES};
+use crate::runtime::timing::Timing;
+use crate::runtime::random::Random;
+
+use std::fmt;
+use std::fs;
+
+// a text file, the seed, length and machine settings up top then one key change a line:
+//
+//   chip-8-interpreter movie 1
+//   seed 1234
+//   frames 600
+//   mode 0
+//   quirks vf_reset=on shifting=off jumping=off memory_increment=on clipping=on display_wait=on
+//   ips 720
+//   timing instructions
+//   random xorshift
+//   12 5 down
+//   30 5 up
+//
+// changes are fed in at the start of their frame, counting from 0. replaying needs the same rom, and the same
+// settings, which is why they're written down
+const HEADER: &str = "chip-8-interpreter movie";
+const MOVIE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyChange {
+    pub frame: usize,
+    pub key: usize,
+    pub pressed: bool,
+}
+
+// everything besides the keys and the seed that a run depends on, anything different and a replay goes out of step
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub mode: Mode,
+    pub quirks: Quirks,
+    pub ips: usize,
+    pub timing: Timing,
+    pub random: String, // the kind of generator, as --random takes it
+}
+
+fn mode_number(mode: Mode) -> usize {
+    match mode {
+        Mode::CHIP8 => 0,
+        Mode::SCHIP => 1,
+        Mode::X0CHIP => 2,
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    return if value { "on" } else { "off" };
+}
+
+impl Machine {
+    pub fn of(runtime: &Runtime) -> Machine {
+        return Machine {
+            mode: runtime.mode,
+            quirks: runtime.quirks,
+            ips: runtime.clock.ips,
+            timing: runtime.timing,
+            random: runtime.random.name(),
+        };
+    }
+
+    // what the runtime does differently, as the options that would put it right
+    pub fn differences(&self, runtime: &Runtime) -> Vec<String> {
+        let now = Machine::of(runtime);
+        let mut differences: Vec<String> = Vec::new();
+        if now.mode != self.mode {
+            differences.push(format!("mode {}", mode_number(self.mode)));
+        }
+        for name in QUIRK_NAMES {
+            if now.quirks.get(name) != self.quirks.get(name) {
+                differences.push(format!("--quirk {}={}", name, on_off(self.quirks.get(name) == Some(true))));
+            }
+        }
+        if now.ips != self.ips {
+            differences.push(format!("--ips {}", self.ips));
+        }
+        if now.timing != self.timing {
+            differences.push(format!("--timing {}", self.timing.name()));
+        }
+        if now.random != self.random {
+            differences.push(format!("--random {}", self.random));
+        }
+        return differences;
+    }
+
+    fn to_text(&self) -> String {
+        let quirks: Vec<String> = QUIRK_NAMES.iter()
+            .map(|name| format!("{}={}", name, on_off(self.quirks.get(name) == Some(true))))
+            .collect();
+        return format!("mode {}\nquirks {}\nips {}\ntiming {}\nrandom {}\n",
+            mode_number(self.mode), quirks.join(" "), self.ips, self.timing.name(), self.random);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movie {
+    pub seed: u64,
+    pub frames: usize, // how long the run went on for, a replay stops here
+    pub machine: Machine,
+    pub changes: Vec<KeyChange>, // in frame order
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MovieError {
+    Io(String),
+    Invalid(usize, String), // line number and what's wrong with it
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::Io(message) => write!(f, "{}", message),
+            MovieError::Invalid(line, message) => write!(f, "bad movie, line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl Movie {
+    // an empty recording of the runtime as it's set up now
+    pub fn new(runtime: &Runtime) -> Movie {
+        return Movie { seed: runtime.seed(), frames: 0, machine: Machine::of(runtime), changes: Vec::new() };
+    }
+
+    pub fn record(&mut self, frame: usize, key: usize, pressed: bool) {
+        self.changes.push(KeyChange { frame, key, pressed });
+        self.frames = self.frames.max(frame + 1);
+    }
+
+    // the key changes that go in at the start of a frame
+    pub fn changes_at(&self, frame: usize) -> impl Iterator<Item = &KeyChange> {
+        let start = self.changes.partition_point(|change| change.frame < frame);
+        return self.changes[start..].iter().take_while(move |change| change.frame == frame);
+    }
+
+    // whether the runtime is set up the way the movie was recorded, and if not what to change
+    pub fn check(&self, runtime: &Runtime) -> Result<(), String> {
+        let differences = self.machine.differences(runtime);
+        if differences.is_empty() {
+            return Ok(());
+        }
+        return Err(format!("it was recorded with {}", differences.join(", ")));
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\nseed {}\nframes {}\n", HEADER, MOVIE_VERSION, self.seed, self.frames);
+        text.push_str(&self.machine.to_text());
+        for change in self.changes.iter() {
+            let direction = if change.pressed { "down" } else { "up" };
+            text.push_str(&format!("{} {:x} {}\n", change.frame, change.key, direction));
+        }
+        return text;
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+        let invalid = |line: usize, message: &str| MovieError::Invalid(line, message.to_string());
+
+        let (_, header) = lines.next().ok_or_else(|| invalid(1, "empty file"))?;
+        let version = header.strip_prefix(HEADER).map(str::trim).ok_or_else(|| invalid(1, "not a movie"))?;
+        if version.parse() != Ok(MOVIE_VERSION) {
+            return Err(invalid(1, &format!("unsupported movie version {}", version)));
+        }
+        // the next header line, which has to be the one named
+        let mut field = |name: &str| -> Result<(usize, &str), MovieError> {
+            let (number, line) = lines.next().ok_or_else(|| invalid(1, &format!("missing {}", name)))?;
+            let value = line.strip_prefix(name).filter(|value| value.starts_with(' '))
+                .ok_or_else(|| invalid(number, &format!("expected {}", name)))?;
+            return Ok((number, value.trim()));
+        };
+        let number = |(line, value): (usize, &str), name: &str| -> Result<u64, MovieError> {
+            return value.parse().map_err(|_| invalid(line, &format!("expected {} and a number", name)));
+        };
+        let seed = number(field("seed")?, "seed")?;
+        let frames = number(field("frames")?, "frames")? as usize;
+        let (line, mode) = field("mode")?;
+        let mode = match mode {
+            "0" => Mode::CHIP8,
+            "1" => Mode::SCHIP,
+            "2" => Mode::X0CHIP,
+            _ => return Err(invalid(line, &format!("bad mode {}", mode))),
+        };
+        let (line, settings) = field("quirks")?;
+        let mut quirks = Quirks::for_mode(mode);
+        for setting in settings.split_whitespace() {
+            let known = match setting.split_once('=') {
+                Some((name, "on")) => quirks.set(name, true),
+                Some((name, "off")) => quirks.set(name, false),
+                _ => false,
+            };
+            if !known {
+                return Err(invalid(line, &format!("bad quirk {}", setting)));
+            }
+        }
+        let ips = number(field("ips")?, "ips")? as usize;
+        let (line, timing) = field("timing")?;
+        let timing = Timing::from_name(timing).ok_or_else(|| invalid(line, &format!("bad timing {}", timing)))?;
+        let (line, random) = field("random")?;
+        let random = Random::from_name(random, 0).ok_or_else(|| invalid(line, &format!("bad random number generator {}", random)))?.name();
+        let machine = Machine { mode, quirks, ips, timing, random };
+
+        let mut changes: Vec<KeyChange> = Vec::new();
+        for (number, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [frame, key, direction] = parts[..] else {
+                return Err(invalid(number, "expected a frame, a hex key and down or up"));
+            };
+            let frame: usize = frame.parse().map_err(|_| invalid(number, &format!("bad frame {}", frame)))?;
+            let key = usize::from_str_radix(key, 16).ok().filter(|&key| key < KEYBOARD_SIZE)
+                .ok_or_else(|| invalid(number, &format!("bad key {}", key)))?;
+            let pressed = match direction {
+                "down" => true,
+                "up" => false,
+                _ => return Err(invalid(number, &format!("expected down or up, got {}", direction))),
+            };
+            if changes.last().is_some_and(|last| last.frame > frame) {
+                return Err(invalid(number, "frames go backwards"));
+            }
+            changes.push(KeyChange { frame, key, pressed });
+        }
+        return Ok(Movie { seed, frames, machine, changes });
+    }
+
+    pub fn load(path: &str) -> Result<Movie, MovieError> {
+        let text = fs::read_to_string(path).map_err(|error| MovieError::Io(format!("could not read {}: {}", path, error)))?;
+        return Movie::parse(&text);
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), MovieError> {
+        return fs::write(path, self.to_text()).map_err(|error| MovieError::Io(format!("could not write {}: {}", path, error)));
+    }
+}
+
+// play a movie back with no window, for regression runs. the runtime should be fresh
+pub fn play(runtime: &mut Runtime, movie: &Movie, frames: usize, audio: &mut dyn Audio) -> Result<(), ExecutionError> {
+    runtime.reseed(movie.seed);
+    for frame in 0..frames {
+        if runtime.is_halted() {
+            break;
+        }
+        runtime.clear_key_edges();
+        for change in movie.changes_at(frame) {
+            runtime.set_key(change.key, change.pressed);
+        }
+        run_frame(runtime, audio)?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::random::Random;
+
+    fn runtime() -> Runtime {
+        return Runtime::new(&[0x12, 0x00], Mode::CHIP8);
+    }
+
+    fn line_of(error: MovieError) -> usize {
+        let MovieError::Invalid(line, _) = error else {
+            panic!("expected a bad line, got {}", error);
+        };
+        return line;
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let mut movie = Movie::new(&runtime());
+        movie.record(3, 0x5, true);
+        movie.record(3, 0xA, true);
+        movie.record(7, 0x5, false);
+        let text = movie.to_text();
+        assert!(text.starts_with("chip-8-interpreter movie 1\n"));
+        assert_eq!(Movie::parse(&text).unwrap(), movie);
+        assert_eq!(movie.frames, 8);
+    }
+
+    #[test]
+    fn changes_come_out_on_their_frame() {
+        let mut movie = Movie::new(&runtime());
+        movie.record(2, 0x1, true);
+        movie.record(2, 0x2, true);
+        movie.record(5, 0x1, false);
+        let keys = |frame| movie.changes_at(frame).map(|change| (change.key, change.pressed)).collect::<Vec<_>>();
+        assert_eq!(keys(0), vec![]);
+        assert_eq!(keys(2), vec![(0x1, true), (0x2, true)]);
+        assert_eq!(keys(3), vec![]);
+        assert_eq!(keys(5), vec![(0x1, false)]);
+        assert_eq!(keys(6), vec![]);
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        let header = Movie::new(&runtime()).to_text();
+        assert_eq!(line_of(Movie::parse("").unwrap_err()), 1);
+        assert_eq!(line_of(Movie::parse("a movie 1\n").unwrap_err()), 1);
+        assert_eq!(line_of(Movie::parse(&header.replacen("movie 1", "movie 2", 1)).unwrap_err()), 1);
+        assert_eq!(line_of(Movie::parse("chip-8-interpreter movie 1\nframes 1\n").unwrap_err()), 2);
+        assert_eq!(line_of(Movie::parse("chip-8-interpreter movie 1\nseed 1\nframes 1\n").unwrap_err()), 1);
+        assert_eq!(line_of(Movie::parse(&format!("{}1 g down\n", header)).unwrap_err()), 9);
+        assert_eq!(line_of(Movie::parse(&format!("{}1 1 sideways\n", header)).unwrap_err()), 9);
+        assert_eq!(line_of(Movie::parse(&format!("{}1 1\n", header)).unwrap_err()), 9);
+        assert_eq!(line_of(Movie::parse(&format!("{}5 1 down\n2 1 up\n", header)).unwrap_err()), 10);
+
+        let mut text = Movie::new(&runtime()).to_text();
+        text = text.replace("timing instructions", "timing sometimes");
+        assert_eq!(line_of(Movie::parse(&text).unwrap_err()), 7);
+        let text = Movie::new(&runtime()).to_text().replace("shifting=off", "shifty=off");
+        assert_eq!(line_of(Movie::parse(&text).unwrap_err()), 5);
+    }
+
+    #[test]
+    fn different_settings_are_caught() {
+        let movie = Movie::new(&runtime());
+        assert!(movie.check(&runtime()).is_ok());
+
+        let mut changed = runtime();
+        changed.quirks.shifting = true;
+        changed.clock.ips = 1000;
+        changed.timing = Timing::Vip;
+        changed.random = Random::from_name("vip", 0).unwrap();
+        assert_eq!(movie.machine.differences(&changed),
+            vec!["--quirk shifting=off", "--ips 720", "--timing instructions", "--random xorshift"]);
+        assert!(movie.check(&changed).is_err());
+
+        let schip = Runtime::new(&[0x12, 0x00], Mode::SCHIP);
+        assert_eq!(movie.machine.differences(&schip)[0], "mode 0");
+    }
+
+    #[test]
+    fn a_different_seed_is_fine() {
+        let movie = Movie::new(&runtime());
+        let mut reseeded = runtime();
+        reseeded.reseed(movie.seed + 1);
+        assert!(movie.check(&reseeded).is_ok());
+    }
+}
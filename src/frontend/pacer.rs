@@ -0,0 +1,60 @@
+use crate::runtime::clock::TIMER_HZ;
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const MAX_CATCH_UP_FRAMES: usize = 5; // any further behind than this and the backlog is dropped
+// how far the speed goes either way, much slower and a frame lasts longer than a duration can hold
+pub const MIN_SPEED: f64 = 0.01;
+pub const MAX_SPEED: f64 = 100.0;
+
+// keeps emulated frames lined up with the wall clock, at 60hz times the speed
+pub struct Pacer {
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+impl Pacer {
+    pub fn new(speed: f64) -> Pacer {
+        let mut pacer = Pacer { frame_duration: Duration::ZERO, next_frame: Instant::now() };
+        pacer.set_speed(speed);
+        return pacer;
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.frame_duration = Duration::from_secs_f64(1.0 / (TIMER_HZ as f64 * speed));
+    }
+
+    // sleep until the next frame is due, then say how many frames are due by now.
+    // the deadlines advance by a fixed step rather than from when we woke up, so oversleeping doesn't drift
+    pub fn wait(&mut self) -> usize {
+        let now = Instant::now();
+        if now < self.next_frame {
+            sleep(self.next_frame - now);
+        }
+        let now = Instant::now();
+        let mut due: usize = 0;
+        while self.next_frame <= now && due < MAX_CATCH_UP_FRAMES {
+            self.next_frame += self.frame_duration;
+            due += 1;
+        }
+        if self.next_frame <= now {
+            // too far behind to catch up (sat in the debugger, window dragged), start afresh
+            self.next_frame = now + self.frame_duration;
+        }
+        return due;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speeds_out_of_range_are_clamped() {
+        assert_eq!(Pacer::new(1e-300).frame_duration, Pacer::new(MIN_SPEED).frame_duration);
+        assert_eq!(Pacer::new(1e300).frame_duration, Pacer::new(MAX_SPEED).frame_duration);
+        assert_eq!(Pacer::new(2.0).frame_duration, Duration::from_secs_f64(1.0 / 120.0));
+    }
+}
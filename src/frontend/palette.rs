@@ -0,0 +1,69 @@
+// rgb for each combination of the two planes: neither, the first, the second, both.
+// only xo-chip draws on the second plane, everything else just needs the first two
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [[u8; 3]; 4],
+}
+
+const PRESETS: [(&str, [[u8; 3]; 4]); 6] = [
+    ("green", [[0x00, 0x00, 0x00], [0x00, 0xFA, 0x00], [0x00, 0x6E, 0x00], [0xB4, 0xFF, 0xB4]]),
+    ("vip", [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF], [0x80, 0x80, 0x80], [0xC0, 0xC0, 0xC0]]),
+    ("amber", [[0x18, 0x0C, 0x00], [0xFF, 0xB0, 0x00], [0x8C, 0x5A, 0x00], [0xFF, 0xDC, 0x78]]),
+    ("lcd", [[0x9B, 0xBC, 0x0F], [0x0F, 0x38, 0x0F], [0x8B, 0xAC, 0x0F], [0x30, 0x62, 0x30]]),
+    ("contrast", [[0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF], [0xFF, 0xFF, 0x00], [0x00, 0xFF, 0xFF]]),
+    ("octo", [[0x99, 0x66, 0x00], [0xFF, 0xCC, 0x00], [0xFF, 0x66, 0x00], [0x66, 0x22, 0x00]]),
+];
+
+pub const PALETTE_NAMES: [&str; 6] = ["green", "vip", "amber", "lcd", "contrast", "octo"];
+
+// "ff8800" or "#ff8800"
+fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok();
+    return Some([channel(0)?, channel(1)?, channel(2)?]);
+}
+
+impl Palette {
+    pub fn preset(name: &str) -> Option<Palette> {
+        return PRESETS.iter().find(|(preset, _)| *preset == name)
+            .map(|&(name, colors)| Palette { name: name.to_string(), colors });
+    }
+
+    // every preset, in the order the hotkey goes through them
+    pub fn presets() -> Vec<Palette> {
+        return PRESETS.iter().map(|&(name, colors)| Palette { name: name.to_string(), colors }).collect();
+    }
+
+    // background and foreground, or all four for xo-chip. with two the foreground stands in for the other planes
+    pub fn from_hex(colors: &[&str]) -> Option<Palette> {
+        let parsed: Vec<[u8; 3]> = colors.iter().map(|color| parse_color(color)).collect::<Option<_>>()?;
+        let colors = match parsed[..] {
+            [background, foreground] => [background, foreground, foreground, foreground],
+            [background, first, second, both] => [background, first, second, both],
+            _ => return None,
+        };
+        return Some(Palette { name: String::from("custom"), colors });
+    }
+
+    // a preset name or comma separated hex colours
+    pub fn from_name(text: &str) -> Option<Palette> {
+        if text.contains(',') {
+            return Palette::from_hex(&text.split(',').collect::<Vec<&str>>());
+        }
+        return Palette::preset(text);
+    }
+
+    pub fn color(&self, pixel: u8) -> [u8; 3] {
+        return self.colors[pixel as usize & 0x03];
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        return Palette::preset("green").expect("the green palette exists");
+    }
+}
@@ -0,0 +1,75 @@
+use crate::frontend::Audio;
+use crate::frontend::sound::{Pattern, SoundSettings, Tone};
+
+use sdl2;
+use sdl2::audio::{AudioDevice, AudioCallback, AudioSpecDesired};
+
+// the device plays all the time and the tone fades in and out inside it, pausing the device would click.
+// the callback runs on sdl's audio thread, so anything it reads is changed with the device locked
+pub struct SdlAudio {
+    device: AudioDevice<Beeper>,
+    beeping: bool,
+    pattern: Option<Pattern>,
+}
+
+impl SdlAudio {
+    // fails when there's no sound device to open, which is the case in most containers
+    pub fn initialize(sdl_context: &sdl2::Sdl, settings: &SoundSettings) -> Result<Self, String> {
+        let audio_subsystem = sdl_context.audio()?;
+
+        let desired_spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1), // mono
+            samples: None, // default sample size
+        };
+
+        let device = audio_subsystem
+            .open_playback(None, &desired_spec, |spec| {
+                // Show obtained AudioSpec
+                println!("{:?}", spec);
+
+                // initialize the audio callback
+                Beeper { tone: Tone::new(settings.clone(), spec.freq as u32) }
+            })?;
+        device.resume();
+
+        return Ok(SdlAudio { device, beeping: false, pattern: None });
+    }
+
+    fn set_beeping(&mut self, beeping: bool) {
+        if beeping != self.beeping {
+            self.beeping = beeping;
+            self.device.lock().tone.on = beeping;
+        }
+    }
+}
+
+impl Audio for SdlAudio {
+    fn start_beep(&mut self) {
+        self.set_beeping(true);
+    }
+    fn stop_beep(&mut self) {
+        self.set_beeping(false);
+    }
+    fn set_sound(&mut self, settings: &SoundSettings) {
+        self.device.lock().tone.settings = settings.clone();
+    }
+    fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        if pattern != self.pattern {
+            self.pattern = pattern;
+            self.device.lock().tone.pattern = pattern;
+        }
+    }
+}
+
+struct Beeper {
+    tone: Tone,
+}
+
+impl AudioCallback for Beeper {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.tone.fill(out);
+    }
+}
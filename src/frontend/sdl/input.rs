@@ -0,0 +1,195 @@
+use crate::frontend::{Input, InputEvent, Command, keymap::KeyMap, gamepad::GamepadMap};
+use crate::runtime::KEYBOARD_SIZE;
+
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::{EventPump, GameControllerSubsystem};
+
+use std::collections::{HashMap, HashSet};
+
+// our key names that sdl spells differently, anything else goes to sdl as it is ("f3", "home"...)
+const KEY_NAMES: [(&str, &str); 31] = [
+    ("escape", "Escape"), ("minus", "-"), ("equals", "="), ("backspace", "Backspace"), ("tab", "Tab"),
+    ("leftbracket", "["), ("rightbracket", "]"), ("enter", "Return"), ("semicolon", ";"), ("apostrophe", "'"),
+    ("grave", "`"), ("backslash", "\\"), ("comma", ","), ("period", "."), ("slash", "/"), ("space", "Space"),
+    ("lctrl", "Left Ctrl"), ("rctrl", "Right Ctrl"), ("lshift", "Left Shift"), ("rshift", "Right Shift"),
+    ("lalt", "Left Alt"), ("kpminus", "Keypad -"), ("kpplus", "Keypad +"), ("kpperiod", "Keypad ."),
+    ("kpenter", "Keypad Enter"), ("kpmultiply", "Keypad *"), ("kpdivide", "Keypad /"),
+    ("up", "Up"), ("down", "Down"), ("left", "Left"), ("right", "Right"),
+];
+
+fn keycode(name: &str) -> Option<Keycode> {
+    if let Some(digit) = name.strip_prefix("kp").filter(|digit| digit.len() == 1) {
+        return Keycode::from_name(&format!("Keypad {}", digit));
+    }
+    let sdl_name = KEY_NAMES.iter().find(|(ours, _)| *ours == name).map(|&(_, theirs)| theirs).unwrap_or(name);
+    return Keycode::from_name(sdl_name);
+}
+
+// something on the keyboard or a controller that can hold a hex key down
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Control {
+    Key(Keycode),
+    Button(Button),
+    Axis(Axis, bool), // true for the positive end of the axis, right and down on the sticks
+}
+
+// "a", "dpup", "leftx-", "triggerleft"...
+fn control(name: &str) -> Option<Control> {
+    if let Some(axis) = name.strip_suffix('+') {
+        return Axis::from_string(axis).map(|axis| Control::Axis(axis, true));
+    }
+    if let Some(axis) = name.strip_suffix('-') {
+        return Axis::from_string(axis).map(|axis| Control::Axis(axis, false));
+    }
+    // triggers only go one way
+    if let Some(axis) = Axis::from_string(name).filter(|axis| matches!(axis, Axis::TriggerLeft | Axis::TriggerRight)) {
+        return Some(Control::Axis(axis, true));
+    }
+    return Button::from_string(name).map(Control::Button);
+}
+
+const KEYBOARD: u32 = u32::MAX; // stands in for a controller id when the keyboard holds something
+
+// f2 takes a screenshot and f3 starts and stops capturing, f4 mutes, f5 and f9 quick save and load, f6 and f7 step
+// through the slots, f8 changes palette, f10 changes waveform, f11 goes fullscreen and f12 breaks into the debugger.
+// page up and page down turn the volume up and down, home and end the tone a semitone at a time.
+// keymap::HOTKEY_NAMES has to list these too, so they can't be bound to the keypad
+const HOTKEYS: [(Keycode, Command); 15] = [
+    (Keycode::F2, Command::Screenshot),
+    (Keycode::F3, Command::ToggleCapture),
+    (Keycode::F4, Command::ToggleMute),
+    (Keycode::F10, Command::NextWaveform),
+    (Keycode::PageUp, Command::VolumeUp),
+    (Keycode::PageDown, Command::VolumeDown),
+    (Keycode::Home, Command::FrequencyUp),
+    (Keycode::End, Command::FrequencyDown),
+    (Keycode::F5, Command::SaveState),
+    (Keycode::F9, Command::LoadState),
+    (Keycode::F6, Command::PreviousSlot),
+    (Keycode::F7, Command::NextSlot),
+    (Keycode::F8, Command::NextPalette),
+    (Keycode::F11, Command::ToggleFullscreen),
+    (Keycode::F12, Command::Break),
+];
+
+// keys from the window's event queue, so nothing is seen while another window has focus.
+// names are looked up as keycodes, which follow the keyboard layout (an azerty "a" is where it's printed).
+// game controllers are opened as they're plugged in and any number of them can play at once
+pub struct SdlInput {
+    event_pump: EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    controllers: HashMap<u32, GameController>, // by instance id, dropping one closes it
+    bindings: Vec<(Control, usize)>, // host control and the hex key it presses
+    dead_zone: i16,
+    held: HashSet<(u32, Control)>, // which controller, or the keyboard, holds what
+    keypad: [bool; KEYBOARD_SIZE], // what we last told the runtime
+}
+
+impl SdlInput {
+    pub fn initialize(sdl_context: &sdl2::Sdl, keymap: &KeyMap, gamepad: &GamepadMap) -> Result<Self, String> {
+        let mut bindings: Vec<(Control, usize)> = Vec::new();
+        for name in keymap.names() {
+            let code = keycode(name).ok_or_else(|| format!("unknown key name {}", name))?;
+            bindings.extend(keymap.keys_for(name).into_iter().map(|key| (Control::Key(code), key)));
+        }
+        for name in gamepad.controls.names() {
+            let control = control(name).ok_or_else(|| format!("unknown gamepad control {}", name))?;
+            bindings.extend(gamepad.controls.keys_for(name).into_iter().map(|key| (control, key)));
+        }
+        let dead_zone = (gamepad.dead_zone * i16::MAX as f32) as i16;
+        // controllers already plugged in turn up as device added events, same as ones plugged in later
+        let controller_subsystem = sdl_context.game_controller()?;
+        let event_pump = sdl_context.event_pump()?;
+        return Ok(SdlInput {
+            event_pump, controller_subsystem, controllers: HashMap::new(),
+            bindings, dead_zone, held: HashSet::new(), keypad: [false; KEYBOARD_SIZE],
+        });
+    }
+
+    fn press(&mut self, which: u32, control: Control, pressed: bool, events: &mut Vec<InputEvent>) {
+        if pressed {
+            self.held.insert((which, control));
+        } else {
+            self.held.remove(&(which, control));
+        }
+        self.refresh_keypad(events);
+    }
+
+    // a stick is a pair of controls, each held once it's pushed past the dead zone
+    fn move_axis(&mut self, which: u32, axis: Axis, value: i16, events: &mut Vec<InputEvent>) {
+        self.press(which, Control::Axis(axis, true), value > self.dead_zone, events);
+        self.press(which, Control::Axis(axis, false), value < -self.dead_zone, events);
+    }
+
+    fn add_controller(&mut self, joystick_index: u32) {
+        match self.controller_subsystem.open(joystick_index) {
+            Ok(controller) => {
+                println!("gamepad connected: {}", controller.name());
+                self.controllers.insert(controller.instance_id(), controller);
+            },
+            Err(error) => eprintln!("could not open gamepad {}: {}", joystick_index, error),
+        }
+    }
+
+    fn remove_controller(&mut self, which: u32, events: &mut Vec<InputEvent>) {
+        if let Some(controller) = self.controllers.remove(&which) {
+            println!("gamepad disconnected: {}", controller.name());
+        }
+        self.held.retain(|&(holder, _)| holder != which);
+        self.refresh_keypad(events);
+    }
+
+    // a hex key is down while any of the host controls bound to it are, report it when that changes
+    fn refresh_keypad(&mut self, events: &mut Vec<InputEvent>) {
+        let mut keypad = [false; KEYBOARD_SIZE];
+        for (control, key) in self.bindings.iter() {
+            keypad[*key] |= self.held.iter().any(|(_, held)| held == control);
+        }
+        for (key, (&now, &before)) in keypad.iter().zip(self.keypad.iter()).enumerate() {
+            if now != before {
+                events.push(InputEvent::Key { key, pressed: now });
+            }
+        }
+        self.keypad = keypad;
+    }
+}
+
+impl Input for SdlInput {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        let mut events: Vec<InputEvent> = Vec::new();
+        // drained every frame, which also keeps the window manager from deciding we've hung
+        while let Some(event) = self.event_pump.poll_event() {
+            match event {
+                Event::Quit { .. } => events.push(InputEvent::Command(Command::Quit)),
+                Event::KeyDown { keycode: Some(code), repeat, .. } => {
+                    if let Some(&(_, command)) = HOTKEYS.iter().find(|(hotkey, _)| *hotkey == code) {
+                        if !repeat {
+                            events.push(InputEvent::Command(command));
+                        }
+                        continue;
+                    }
+                    self.press(KEYBOARD, Control::Key(code), true, &mut events);
+                },
+                Event::KeyUp { keycode: Some(code), .. } => self.press(KEYBOARD, Control::Key(code), false, &mut events),
+                Event::ControllerButtonDown { which, button, .. } => self.press(which, Control::Button(button), true, &mut events),
+                Event::ControllerButtonUp { which, button, .. } => self.press(which, Control::Button(button), false, &mut events),
+                Event::ControllerAxisMotion { which, axis, value, .. } => self.move_axis(which, axis, value, &mut events),
+                Event::ControllerDeviceAdded { which, .. } => self.add_controller(which),
+                Event::ControllerDeviceRemoved { which, .. } => self.remove_controller(which, &mut events),
+                // the key releases would go to whichever window has focus now, so let go of them.
+                // controllers don't report to a window and carry on as they were
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                    self.held.retain(|&(holder, _)| holder != KEYBOARD);
+                    self.refresh_keypad(&mut events);
+                },
+                Event::Window { win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed, .. } => {
+                    events.push(InputEvent::Command(Command::Redraw));
+                },
+                _ => {},
+            }
+        }
+        return events;
+    }
+}
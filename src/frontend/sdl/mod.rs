@@ -0,0 +1,38 @@
+mod video;
+mod audio;
+mod input;
+
+pub use video::SdlVideo;
+pub use audio::SdlAudio;
+pub use input::SdlInput;
+
+use crate::frontend::{Audio, Host, config::Config};
+use crate::frontend::audio::{AudioOutput, NullAudio, WavAudio};
+use crate::runtime::Runtime;
+
+// open a window for the runtime and send its sound where it's asked to go, with keys and gamepads coming from the window's events
+pub fn initialize(runtime: Runtime, config: &Config, output: &AudioOutput) -> Result<Host, String> {
+    let sdl_context = sdl2::init()?;
+    let video: SdlVideo = SdlVideo::initialize(&sdl_context, &config.video)?;
+    let audio: Box<dyn Audio> = match output {
+        // carry on without sound rather than not at all
+        AudioOutput::Sdl => match SdlAudio::initialize(&sdl_context, &config.sound) {
+            Ok(audio) => Box::new(audio),
+            Err(error) => {
+                eprintln!("no sound: {}", error);
+                Box::new(NullAudio)
+            },
+        },
+        AudioOutput::Null => Box::new(NullAudio),
+        AudioOutput::Wav(path) => Box::new(WavAudio::create(path, &config.sound)?),
+    };
+    let input: SdlInput = SdlInput::initialize(&sdl_context, &config.keymap, &config.gamepad)?;
+    let mut host = Host::new(runtime, Box::new(video), audio, Box::new(input));
+    host.flicker = config.video.flicker;
+    host.sound = config.sound.clone();
+    // the palette hotkey starts from the chosen one and goes on round the presets
+    let palette = &config.video.palette;
+    host.palettes.retain(|preset| preset.name != palette.name);
+    host.palettes.insert(0, palette.clone());
+    return Ok(host);
+}
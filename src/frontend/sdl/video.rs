@@ -0,0 +1,125 @@
+use crate::frontend::Video;
+use crate::frontend::video::{Compositor, Fit, VideoSettings, fit_rect};
+use crate::frontend::palette::Palette;
+use crate::frontend::filter::{Filter, Image, apply_chain};
+use crate::runtime::display::{Display, CHIP8_WIDTH, CHIP8_HEIGHT};
+
+use sdl2;
+use sdl2::pixels::{self, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext};
+
+pub struct SdlVideo {
+    canvas: Canvas<Window>, // the actually drawn window
+    texture_creator: TextureCreator<WindowContext>,
+    // one texel per machine pixel, or the filtered picture, scaled up by the gpu. made again when the size changes
+    texture: Option<(Texture, usize, usize)>,
+    screen: (u32, u32), // the machine's resolution, which the fit goes by whatever the filters did
+    fit: Fit,
+    palette: Palette,
+    compositor: Compositor,
+    filters: Vec<Filter>,
+}
+
+impl SdlVideo {
+    pub fn initialize(sdl_context: &sdl2::Sdl, settings: &VideoSettings) -> Result<Self, String> {
+        let video_subsys = sdl_context.video()?;
+        let mut window = video_subsys
+            .window(
+                "CHIP-8 Display",
+                CHIP8_WIDTH as u32 * settings.scale,
+                CHIP8_HEIGHT as u32 * settings.scale,
+            )
+            .position_centered()
+            .resizable()
+            .opengl()
+            .build()
+            .map_err(|error| format!("could not open the window: {}", error))?;
+        if settings.fullscreen {
+            let _ = window.set_fullscreen(FullscreenType::Desktop);
+        }
+
+        let canvas = window.into_canvas().build().map_err(|error| format!("could not draw to the window: {}", error))?;
+        let texture_creator = canvas.texture_creator();
+
+        let mut video = SdlVideo {
+            canvas, texture_creator, texture: None, screen: (CHIP8_WIDTH as u32, CHIP8_HEIGHT as u32), fit: settings.fit,
+            palette: settings.palette.clone(), compositor: Compositor::new(settings.flicker), filters: settings.filters.clone(),
+        };
+        // a blank screen to start with, which makes the texture now so a failure there stops us here
+        video.draw(&Display::initialize())?;
+        return Ok(video);
+    }
+
+    // the streaming texture for a size, swapping the old one out on a lores/hires switch
+    fn texture(&mut self, width: usize, height: usize) -> Result<&mut Texture, String> {
+        if !matches!(self.texture, Some((_, w, h)) if w == width && h == height) {
+            if let Some((old, _, _)) = self.texture.take() {
+                // textures aren't freed on drop with unsafe_textures
+                unsafe { old.destroy(); }
+            }
+            let texture = self.texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                .map_err(|error| format!("could not create the screen texture: {}", error))?;
+            self.texture = Some((texture, width, height));
+        }
+        return Ok(&mut self.texture.as_mut().unwrap().0);
+    }
+
+    // the last frame, fitted to however big the window is now
+    fn show(&mut self) {
+        let Some((texture, _, _)) = &self.texture else {
+            return;
+        };
+        let window_size = self.canvas.output_size().unwrap_or((1, 1));
+        let (x, y, width, height) = fit_rect(window_size, self.screen, self.fit);
+        self.canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        let _ = self.canvas.copy(texture, None, Rect::new(x, y, width, height));
+        self.canvas.present();
+    }
+
+    // the display through the compositor and filters onto the screen
+    fn draw(&mut self, display: &Display) -> Result<(), String> {
+        let frame: Vec<u8> = self.compositor.frame(display, &self.palette);
+        let image: Image = apply_chain(&self.filters, Image::new(display.width, display.height, frame));
+        self.screen = (display.width as u32, display.height as u32);
+        let _ = self.texture(image.width, image.height)?.update(None, &image.data, image.width * 3);
+        self.show();
+        return Ok(());
+    }
+}
+
+impl Video for SdlVideo {
+    // there's nowhere to hand an error back to once it's running, so it goes in the title instead
+    fn present(&mut self, display: &Display) {
+        if let Err(error) = self.draw(display) {
+            self.show_status(&error);
+        }
+    }
+
+    fn refresh(&mut self, display: &Display) {
+        match self.texture {
+            Some(_) => self.show(),
+            None => self.present(display),
+        }
+    }
+
+    fn show_status(&mut self, status: &str) {
+        let _ = self.canvas.window_mut().set_title(&format!("CHIP-8 Display - {}", status));
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.palette = palette.clone();
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let next = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        let _ = window.set_fullscreen(next);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::runtime::{Runtime, AUDIO_PATTERN_SIZE};
+
+use std::f32::consts::TAU;
+
+const RAMP_SECONDS: f32 = 0.005; // how long the tone takes to fade in and out, short enough not to hear but long enough not to click
+pub const VOLUME_STEP: f32 = 0.05; // how much the volume hotkeys change it by
+pub const MAX_FREQUENCY: f64 = 20000.0; // anything higher nobody would hear
+const FREQUENCY_STEP: f32 = 1.059463; // how much the tone hotkeys change it by, a semitone
+const MIN_HOTKEY_FREQUENCY: f32 = 20.0; // the tone hotkeys don't go below what anyone can hear either
+
+// the tone a semitone up or down from a frequency, kept within hearing
+pub fn step_frequency(frequency: f32, up: bool) -> f32 {
+    let frequency = if up { frequency * FREQUENCY_STEP } else { frequency / FREQUENCY_STEP };
+    return frequency.clamp(MIN_HOTKEY_FREQUENCY, MAX_FREQUENCY as f32);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sine,
+    Sawtooth,
+}
+
+pub const WAVEFORM_NAMES: [&str; 4] = ["square", "triangle", "sine", "sawtooth"];
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name {
+            "square" => Some(Waveform::Square),
+            "triangle" => Some(Waveform::Triangle),
+            "sine" => Some(Waveform::Sine),
+            "sawtooth" | "saw" => Some(Waveform::Sawtooth),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Waveform::Square => "square",
+            Waveform::Triangle => "triangle",
+            Waveform::Sine => "sine",
+            Waveform::Sawtooth => "sawtooth",
+        }
+    }
+
+    // the one after, for the hotkey to go round them
+    pub fn next(self) -> Waveform {
+        match self {
+            Waveform::Square => Waveform::Triangle,
+            Waveform::Triangle => Waveform::Sine,
+            Waveform::Sine => Waveform::Sawtooth,
+            Waveform::Sawtooth => Waveform::Square,
+        }
+    }
+
+    // -1 to 1, over a cycle from 0 to 1
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sine => (TAU * phase).sin(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+// what the buzzer sounds like
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundSettings {
+    pub frequency: f32,
+    pub waveform: Waveform,
+    pub volume: f32, // 0 to 1
+    pub muted: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        return SoundSettings { frequency: 240.0, waveform: Waveform::Square, volume: 0.25, muted: false };
+    }
+}
+
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+
+// an xo-chip program's own sound: 128 one bit samples looped at a rate set by the pitch register
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pattern {
+    pub bits: [u8; AUDIO_PATTERN_SIZE],
+    pub pitch: u8,
+}
+
+impl Pattern {
+    // what the program wants played, if it's loaded a pattern
+    pub fn from_runtime(runtime: &Runtime) -> Option<Pattern> {
+        if !runtime.pattern_loaded {
+            return None;
+        }
+        return Some(Pattern { bits: runtime.audio_pattern.map(|byte| byte as u8), pitch: runtime.pitch as u8 });
+    }
+
+    // samples a second, 4000 at the default pitch of 64 and an octave for every 48 either side
+    pub fn rate(&self) -> f32 {
+        return 4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0);
+    }
+
+    // most significant bit first
+    fn sample(&self, index: usize) -> f32 {
+        let bit = (self.bits[index / 8] >> (7 - index % 8)) & 1;
+        return if bit == 1 { 1.0 } else { -1.0 };
+    }
+}
+
+// makes the buzzer's samples. it's told when the buzzer goes on and off and ramps in and out from there
+#[derive(Debug, Clone)]
+pub struct Tone {
+    pub settings: SoundSettings,
+    pub on: bool,
+    pub pattern: Option<Pattern>, // played in place of the waveform when there is one
+    sample_rate: f32,
+    phase: f32,
+    position: f32, // how far through the pattern, in bits
+    level: f32, // where the ramp has got to, 0 to 1
+}
+
+impl Tone {
+    pub fn new(settings: SoundSettings, sample_rate: u32) -> Tone {
+        return Tone { settings, on: false, pattern: None, sample_rate: sample_rate as f32, phase: 0.0, position: 0.0, level: 0.0 };
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        let target = if self.on && !self.settings.muted { 1.0 } else { 0.0 };
+        let ramp = 1.0 / (RAMP_SECONDS * self.sample_rate);
+        let step = self.settings.frequency / self.sample_rate;
+        let pattern_step = self.pattern.map(|pattern| pattern.rate() / self.sample_rate).unwrap_or(0.0);
+        for sample in out.iter_mut() {
+            self.level = if self.level < target { (self.level + ramp).min(target) } else { (self.level - ramp).max(target) };
+            let wave = match &self.pattern {
+                Some(pattern) => {
+                    let wave = pattern.sample(self.position as usize);
+                    self.position = (self.position + pattern_step) % PATTERN_BITS;
+                    wave
+                },
+                None => self.settings.waveform.sample(self.phase),
+            };
+            *sample = wave * self.level * self.settings.volume;
+            self.phase = (self.phase + step) % 1.0;
+        }
+    }
+}
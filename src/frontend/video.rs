@@ -0,0 +1,148 @@
+use crate::frontend::palette::Palette;
+use crate::frontend::filter::Filter;
+use crate::runtime::display::Display;
+
+// how the picture fills a window that isn't its shape, the rest is black bars
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit {
+    Integer, // whole multiples of the machine's pixels, so every pixel is the same size
+    Aspect, // as big as it goes while keeping the shape
+}
+
+pub const FIT_NAMES: [&str; 2] = ["integer", "aspect"];
+
+impl Fit {
+    pub fn from_name(name: &str) -> Option<Fit> {
+        match name {
+            "integer" => Some(Fit::Integer),
+            "aspect" => Some(Fit::Aspect),
+            _ => None,
+        }
+    }
+}
+
+// what gets shown of the sprites games flicker by erasing and redrawing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flicker {
+    Draw, // show every change as it's drawn, the way the screen really went
+    Frame, // show the screen once a frame, so anything erased and redrawn within one never goes missing
+    Blend, // once a frame, with the last frame's pixels on as well
+    Phosphor, // once a frame, and lit pixels fade out over a few frames like a crt's phosphor
+}
+
+pub const FLICKER_NAMES: [&str; 4] = ["draw", "frame", "blend", "phosphor"];
+
+impl Flicker {
+    pub fn from_name(name: &str) -> Option<Flicker> {
+        match name {
+            "draw" => Some(Flicker::Draw),
+            "frame" => Some(Flicker::Frame),
+            "blend" => Some(Flicker::Blend),
+            "phosphor" => Some(Flicker::Phosphor),
+            _ => None,
+        }
+    }
+
+    // whether a frame has to be shown even when nothing changed, since what's shown depends on the frames before
+    pub fn every_frame(self) -> bool {
+        return matches!(self, Flicker::Blend | Flicker::Phosphor);
+    }
+}
+
+const PHOSPHOR_DECAY: f32 = 0.6; // how much of a pixel's glow is left a frame later
+
+pub const DEFAULT_SCALE: u32 = 20; // window pixels to a lores pixel
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoSettings {
+    pub scale: u32, // starting window size, in window pixels to a lores pixel
+    pub fit: Fit,
+    pub fullscreen: bool,
+    pub palette: Palette,
+    pub flicker: Flicker,
+    pub filters: Vec<Filter>, // run over every frame in order
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        return VideoSettings {
+            scale: DEFAULT_SCALE,
+            fit: Fit::Aspect,
+            fullscreen: false,
+            palette: Palette::default(),
+            flicker: Flicker::Draw,
+            filters: Vec::new(),
+        };
+    }
+}
+
+// where a picture goes in a window: x, y, width and height, centred
+pub fn fit_rect(window: (u32, u32), picture: (u32, u32), fit: Fit) -> (i32, i32, u32, u32) {
+    let (window_width, window_height) = window;
+    let (picture_width, picture_height) = picture;
+    let (width, height) = match fit {
+        Fit::Integer => {
+            let scale = (window_width / picture_width).min(window_height / picture_height).max(1);
+            (picture_width * scale, picture_height * scale)
+        },
+        Fit::Aspect => {
+            let scale = (window_width as f64 / picture_width as f64).min(window_height as f64 / picture_height as f64);
+            (((picture_width as f64 * scale) as u32).max(1), ((picture_height as f64 * scale) as u32).max(1))
+        },
+    };
+    let x = (window_width as i32 - width as i32) / 2;
+    let y = (window_height as i32 - height as i32) / 2;
+    return (x, y, width, height);
+}
+
+// the screen as rgb bytes, a row at a time
+pub fn rgb_frame(display: &Display, palette: &Palette) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity(display.width * display.height * 3);
+    for row in display.rows() {
+        for &pixel in row {
+            data.extend_from_slice(&palette.color(pixel));
+        }
+    }
+    return data;
+}
+
+// turns screens into rgb frames, keeping hold of what it showed last for the flicker modes that mix frames
+pub struct Compositor {
+    pub flicker: Flicker,
+    previous: Vec<u8>, // the last screen's pixels, for blending
+    glow: Vec<f32>, // the last frame's rgb, for the phosphor to fade
+}
+
+impl Compositor {
+    pub fn new(flicker: Flicker) -> Compositor {
+        return Compositor { flicker, previous: Vec::new(), glow: Vec::new() };
+    }
+
+    pub fn frame(&mut self, display: &Display, palette: &Palette) -> Vec<u8> {
+        match self.flicker {
+            Flicker::Draw | Flicker::Frame => return rgb_frame(display, palette),
+            Flicker::Blend => {
+                let pixels: Vec<u8> = display.rows().concat();
+                // a resolution change starts over
+                if self.previous.len() != pixels.len() {
+                    self.previous = pixels.clone();
+                }
+                let frame = pixels.iter().zip(self.previous.iter())
+                    .flat_map(|(&now, &before)| palette.color(now | before))
+                    .collect();
+                self.previous = pixels;
+                return frame;
+            },
+            Flicker::Phosphor => {
+                let frame: Vec<u8> = rgb_frame(display, palette);
+                if self.glow.len() != frame.len() {
+                    self.glow = frame.iter().map(|&channel| channel as f32).collect();
+                }
+                for (glow, &channel) in self.glow.iter_mut().zip(frame.iter()) {
+                    *glow = (channel as f32).max(*glow * PHOSPHOR_DECAY);
+                }
+                return self.glow.iter().map(|&glow| glow as u8).collect();
+            },
+        }
+    }
+}
@@ -0,0 +1,7 @@
+// explicit returns and opcode-named handlers (handle8XY6 and friends) are house style
+#![allow(clippy::needless_return, non_snake_case)]
+
+pub mod runtime;
+pub mod frontend;
+pub mod disassembler;
+pub mod assembler;
@@ -1,31 +1,291 @@
-mod runtime;
-use runtime::{Runtime,Mode};
+#![allow(clippy::needless_return)]
+
+mod cli;
+
+use chip_8_interpreter::runtime::{Runtime, Mode, clock::Clock};
+use chip_8_interpreter::{assembler, disassembler};
+#[cfg(feature = "sdl")]
+use chip_8_interpreter::runtime::{State, error::ExecutionError};
+use chip_8_interpreter::frontend::headless::{self, ScreenFormat};
+use chip_8_interpreter::frontend::movie::{self, Movie};
+use chip_8_interpreter::frontend::Audio;
+use chip_8_interpreter::frontend::audio::{AudioOutput, NullAudio, WavAudio};
+use chip_8_interpreter::frontend::sound::SoundSettings;
+use cli::Options;
 use std::env;
+use std::fs;
+use std::io::{stdout, Write};
 use std::process::exit;
-use ctrlc;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "sdl")]
+use chip_8_interpreter::frontend::config::{self, Config};
+#[cfg(feature = "sdl")]
+use chip_8_interpreter::frontend::gamepad::GamepadMap;
+#[cfg(feature = "sdl")]
+use std::path::PathBuf;
+#[cfg(feature = "sdl")]
+use std::thread::sleep;
+#[cfg(feature = "sdl")]
+use std::time::Duration;
 
+// set while the window's main loop is running, which takes care of quitting on ctrl-c itself
+static WINDOW_OPEN: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
 
 fn main() {
-    // despite alleged default handling for SIGINT, I needed this for it to work
+    // despite alleged default handling for SIGINT, I needed this for it to work.
+    // the window quits the same way as closing it, so captures and recordings get saved. a second ctrl-c
+    // doesn't wait, for when it's stuck (at the debugger's prompt, say)
     ctrlc::set_handler(move || {
-        exit(1);
+        if !WINDOW_OPEN.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst) {
+            exit(1);
+        }
     }).expect("Error setting Ctrl-C handler");
     let args: Vec<String> = env::args().collect();
-    println!("{:?}", args);
-    let mode_arg = if args.len() < 3 {
-        "0"
+    if args.get(1).map(|arg| arg.as_str()) == Some("disasm") {
+        let (file_name, mode) = cli::parse_disasm(args);
+        disassemble(&file_name, mode);
+        return;
+    }
+    if args.get(1).map(|arg| arg.as_str()) == Some("asm") {
+        let (source, mode, output) = cli::parse_asm(args);
+        assemble(&source, mode, &output);
+        return;
+    }
+    let options: Options = cli::parse(args);
+    let mut runtime: Runtime = Runtime::initialize(options.file_name.clone(), options.mode);
+    runtime.quirks = options.quirks;
+    runtime.timing = options.timing;
+    if let Some(ips) = options.ips {
+        runtime.clock = Clock::new(ips);
+    }
+    runtime.trace = options.trace;
+    if let Some(random) = options.random.clone() {
+        runtime.random = random;
+    }
+    runtime.reseed(options.seed.unwrap_or(runtime.seed()));
+    if let Some(path) = &options.load_state {
+        if let Err(error) = runtime.load_state_file(path) {
+            eprintln!("{}", error);
+            exit(1);
+        }
+    }
+    if options.headless {
+        run_headless(runtime, options);
     } else {
-        args[2].as_str()
+        run(runtime, options);
+    }
+}
+
+// print a listing of the rom as it would be loaded at 0x200
+fn disassemble(file_name: &str, mode: Mode) {
+    let rom: Vec<u8> = fs::read(file_name).unwrap_or_else(|error| {
+        eprintln!("could not read {}: {}", file_name, error);
+        exit(1);
+    });
+    print!("{}", disassembler::format_listing(&disassembler::disassemble(&rom, mode)));
+}
+
+// assemble a source file into a rom
+fn assemble(source: &str, mode: Mode, output: &str) {
+    let text: String = fs::read_to_string(source).unwrap_or_else(|error| {
+        eprintln!("could not read {}: {}", source, error);
+        exit(1);
+    });
+    match assembler::assemble(&text, mode) {
+        Ok(rom) => fs::write(output, rom).unwrap_or_else(|_| panic!("could not write rom to {}", output)),
+        Err(error) => {
+            eprintln!("{}: {}", source, error);
+            exit(1);
+        },
+    }
+}
+
+// a movie to replay on the runtime, which has to be set up the way it was when it was recorded
+fn load_movie(path: &str, runtime: &Runtime) -> Movie {
+    let movie = Movie::load(path).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        exit(1);
+    });
+    if let Err(message) = movie.check(runtime) {
+        eprintln!("{}: won't replay, {}", path, message);
+        exit(1);
+    }
+    return movie;
+}
+
+// the buzzer's sound from the command line, on top of what it was going to be
+fn apply_sound_options(sound: &mut SoundSettings, options: &Options) {
+    if let Some(frequency) = options.frequency {
+        sound.frequency = frequency;
+    }
+    if let Some(waveform) = options.waveform {
+        sound.waveform = waveform;
+    }
+    if let Some(volume) = options.volume {
+        sound.volume = volume;
+    }
+    sound.muted |= options.mute;
+}
+
+// run without any window, then write out the screen and machine state. the sound goes nowhere unless it's asked for in a wav
+fn run_headless(mut runtime: Runtime, options: Options) {
+    let mut audio: Box<dyn Audio> = match &options.audio {
+        Some(AudioOutput::Wav(path)) => {
+            let mut sound = SoundSettings::default();
+            apply_sound_options(&mut sound, &options);
+            Box::new(WavAudio::create(path, &sound).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                exit(1);
+            }))
+        },
+        _ => Box::new(NullAudio),
+    };
+    let mut result = Ok(());
+    if let Some(cycles) = options.cycles {
+        result = headless::run_cycles(&mut runtime, cycles, audio.as_mut());
+    }
+    if let Some(path) = &options.replay {
+        // a replay runs for as long as the recording did unless it's cut short
+        let movie = load_movie(path, &runtime);
+        let frames = options.frames.unwrap_or(movie.frames);
+        result = movie::play(&mut runtime, &movie, frames, audio.as_mut());
+    } else if let Some(frames) = options.frames {
+        result = result.and_then(|_| headless::run_frames(&mut runtime, frames, audio.as_mut()));
+    }
+    if let Err(error) = audio.finish() {
+        eprintln!("{}", error);
+    }
+
+    // pick the format from the flag, then the file extension, then fall back to ascii
+    let format: ScreenFormat = options.screen_format.unwrap_or_else(|| {
+        options.screen_output.as_ref()
+            .and_then(|path| path.rsplit('.').next())
+            .and_then(ScreenFormat::from_name)
+            .unwrap_or(ScreenFormat::Ascii)
+    });
+    let screen: Vec<u8> = headless::render_screen(
+        &runtime.display, &format, &options.palette.clone().unwrap_or_default(), options.filters.as_deref().unwrap_or_default());
+    let dump: String = headless::state_dump(&runtime);
+
+    match &options.screen_output {
+        Some(path) => fs::write(path, screen).unwrap_or_else(|_| panic!("could not write screen to {}", path)),
+        None => stdout().write_all(&screen).expect("could not write screen"),
+    }
+    match &options.dump_output {
+        Some(path) => fs::write(path, dump).unwrap_or_else(|_| panic!("could not write dump to {}", path)),
+        None if options.screen_output.is_none() => print!("{}", dump),
+        None => {},
+    }
+    if let Some(path) = &options.save_state {
+        if let Err(error) = runtime.save_state_file(path) {
+            eprintln!("{}", error);
+        }
+    }
+    if let Err(error) = result {
+        eprintln!("halted: {}", error);
+        exit(1);
+    }
+}
+
+// the config from --config, or the default one if it's there, or the built in settings
+#[cfg(feature = "sdl")]
+fn load_config(options: &Options) -> Config {
+    let path: Option<PathBuf> = match &options.config {
+        Some(path) => Some(PathBuf::from(path)),
+        None => config::default_path().filter(|path| path.exists()),
+    };
+    let Some(path) = path else {
+        return Config::default();
     };
-    let mode: Mode;
-    match mode_arg {
-        "0" => mode = Mode::CHIP8,
-        "1" => mode = Mode::SCHIP,
-        "2" => mode = Mode::X0CHIP,
-        _ => panic!("bad compatability mode selected: {}", args[2]),
-    }
-    let mut runtime: Runtime = Runtime::initialize(args[1].clone(), mode);
+    return Config::load(&path, &options.file_name).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path.display(), error);
+        exit(1);
+    });
+}
+
+#[cfg(feature = "sdl")]
+fn run(runtime: Runtime, options: Options) {
+    let mut config = load_config(&options);
+    apply_sound_options(&mut config.sound, &options);
+    for (key, names) in options.binds {
+        config.keymap.bind(key, names);
+    }
+    if let Some(scale) = options.scale {
+        config.video.scale = scale;
+    }
+    if let Some(fit) = options.fit {
+        config.video.fit = fit;
+    }
+    config.video.fullscreen |= options.fullscreen;
+    if let Some(palette) = options.palette.clone() {
+        config.video.palette = palette;
+    }
+    if let Some(flicker) = options.flicker {
+        config.video.flicker = flicker;
+    }
+    if let Some(filters) = options.filters.clone() {
+        config.video.filters = filters;
+    }
+    // a layout from the command line keeps the config's dead zone
+    if let Some(gamepad) = options.gamepad {
+        config.gamepad = GamepadMap { dead_zone: config.gamepad.dead_zone, ..gamepad };
+    }
+    let mut host = chip_8_interpreter::frontend::sdl::initialize(runtime, &config, options.audio.as_ref().unwrap_or(&AudioOutput::Sdl)).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        exit(1);
+    });
+    host.save_state_base = options.file_name;
+    host.pacer.set_speed(options.speed);
+    if let Some(path) = &options.replay {
+        let movie = load_movie(path, &host.runtime);
+        host.start_playback(movie);
+    }
+    if options.record.is_some() {
+        host.start_recording();
+    }
+    if let Some(scale) = options.screenshot_scale {
+        host.screenshot_scale = scale;
+    }
+    if options.capture.is_some() || options.capture_audio.is_some() {
+        host.start_capture(options.capture.as_deref(), options.capture_audio.as_deref());
+    }
+    host.debugger.breakpoints.extend(options.breakpoints);
+    if options.debug {
+        host.debugger.pause();
+    }
+    let mut reported: Option<ExecutionError> = None;
+    WINDOW_OPEN.store(true, Ordering::SeqCst);
     loop {
-        runtime.frame();
+        match host.run_due_frames() {
+            Err(error) => {
+                // leave the window up on the last frame rather than taking the state down with us,
+                // and keep polling so a save state can still be loaded
+                if reported != Some(error) {
+                    eprintln!("halted: {}", error);
+                    eprint!("{}", headless::register_dump(&host.runtime));
+                    reported = Some(error);
+                }
+                sleep(Duration::from_millis(100));
+            },
+            Ok(()) => reported = None,
+        }
+        host.quit |= INTERRUPTED.load(Ordering::SeqCst);
+        if host.quit || host.runtime.state == State::Exited {
+            host.finish();
+            if let (Some(path), Some(movie)) = (&options.record, &host.recording) {
+                match movie.save(path) {
+                    Ok(()) => println!("recorded {} frames to {}", movie.frames, path),
+                    Err(error) => eprintln!("{}", error),
+                }
+            }
+            exit(0);
+        }
     }
 }
+
+#[cfg(not(feature = "sdl"))]
+fn run(_runtime: Runtime, _options: Options) {
+    eprintln!("built without the sdl feature, only --headless is available");
+    exit(1);
+}
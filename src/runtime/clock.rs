@@ -0,0 +1,32 @@
+use crate::runtime::CALC_PER_FRAME;
+
+pub const TIMER_HZ: usize = 60;
+pub const DEFAULT_IPS: usize = CALC_PER_FRAME * TIMER_HZ;
+
+// spreads a whole number of instructions per second over the 60hz frames, so a second of frames
+// always runs exactly ips instructions no matter how they divide
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Clock {
+    pub ips: usize,
+    pub(crate) frame: usize, // frames into the current second
+}
+
+impl Clock {
+    pub fn new(ips: usize) -> Clock {
+        return Clock { ips, frame: 0 };
+    }
+
+    // how many instructions to run before the next timer tick
+    pub fn next_frame(&mut self) -> usize {
+        let before = self.frame * self.ips / TIMER_HZ;
+        self.frame = (self.frame + 1) % TIMER_HZ;
+        let after = if self.frame == 0 { self.ips } else { self.frame * self.ips / TIMER_HZ };
+        return after - before;
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        return Clock::new(DEFAULT_IPS);
+    }
+}
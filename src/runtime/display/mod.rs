@@ -1,75 +1,128 @@
-use sdl2;
-use sdl2::pixels;
-use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-
 pub const CHIP8_WIDTH: usize = 64;
 pub const CHIP8_HEIGHT: usize = 32;
-const SCALE_FACTOR: u32 = 20;
-const SCREEN_WIDTH: u32 = CHIP8_WIDTH as u32 * SCALE_FACTOR;
-const SCREEN_HEIGHT: u32 = CHIP8_HEIGHT as u32 * SCALE_FACTOR;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+pub const NUM_PLANES: usize = 2; // x0-chip has two bit-planes, everything else only uses the first
 
+// the machine's framebuffer, a front end decides how it actually gets shown
 pub struct Display {
-    canvas: Canvas<Window>, // the actually drawn window
-    representation: [[bool; CHIP8_WIDTH]; CHIP8_HEIGHT] // the internal machine's represantation of above,
+    representation: Vec<Vec<u8>>, // indexed [y][x] with a bit per plane
+    pub width: usize,
+    pub height: usize,
+    pub selected_planes: usize, // bitmask of the planes that clear, scroll and draw act on
+    changed: bool, // whether there is anything new to present
 }
 
 impl Display {
-    pub fn initialize(sdl_context: &sdl2::Sdl) -> Self {
-        let video_subsys = sdl_context.video().unwrap();
-        let window = video_subsys
-            .window(
-                "CHIP-8 Display",
-                SCREEN_WIDTH,
-                SCREEN_HEIGHT,
-            )
-            .position_centered()
-            .opengl()
-            .build()
-            .unwrap();
+    pub fn initialize() -> Self {
+        return Display {
+            representation: vec![vec![0; CHIP8_WIDTH]; CHIP8_HEIGHT],
+            width: CHIP8_WIDTH,
+            height: CHIP8_HEIGHT,
+            selected_planes: 1,
+            changed: true,
+        }
+    }
+
+    // switch between 64x32 and 128x64, which clears the screen like schip does
+    pub fn set_hires(&mut self, hires: bool) {
+        if hires {
+            self.width = HIRES_WIDTH;
+            self.height = HIRES_HEIGHT;
+        } else {
+            self.width = CHIP8_WIDTH;
+            self.height = CHIP8_HEIGHT;
+        }
+        // a resolution change wipes every plane, not just the selected ones
+        self.representation = vec![vec![0; self.width]; self.height];
+        self.changed = true;
+    }
+
+    pub fn clear(&mut self) {
+        let keep: u8 = !(self.selected_planes as u8);
+        for row in self.representation.iter_mut() {
+            for pixel in row.iter_mut() {
+                *pixel &= keep;
+            }
+        }
+        self.changed = true;
+    }
 
-        let mut canvas = window.into_canvas().build().unwrap();
+    pub fn scroll_down(&mut self, rows: usize) {
+        let rows = rows.min(self.height);
+        self.scroll(|x, y| if y >= rows { Some((x, y - rows)) } else { None });
+    }
 
-        canvas.set_draw_color(pixels::Color::RGB(0, 0, 0));
-        canvas.clear();
-        canvas.present();
+    pub fn scroll_up(&mut self, rows: usize) {
+        let rows = rows.min(self.height);
+        let height = self.height;
+        self.scroll(|x, y| if y + rows < height { Some((x, y + rows)) } else { None });
+    }
 
-        return Display { canvas: canvas, representation: [[false; CHIP8_WIDTH]; CHIP8_HEIGHT] }
+    pub fn scroll_right(&mut self, columns: usize) {
+        let columns = columns.min(self.width);
+        self.scroll(|x, y| if x >= columns { Some((x - columns, y)) } else { None });
     }
 
-    pub fn clear(&mut self) {
-        self.canvas.clear();
-        self.representation = [[false; CHIP8_WIDTH]; CHIP8_HEIGHT];
+    pub fn scroll_left(&mut self, columns: usize) {
+        let columns = columns.min(self.width);
+        let width = self.width;
+        self.scroll(|x, y| if x + columns < width { Some((x + columns, y)) } else { None });
     }
 
-    pub fn draw(&mut self, flips: [[bool; CHIP8_WIDTH]; CHIP8_HEIGHT]) -> bool {
+    // move the selected planes, source gives where each pixel's new value comes from
+    fn scroll(&mut self, source: impl Fn(usize, usize) -> Option<(usize, usize)>) {
+        let mask: u8 = self.selected_planes as u8;
+        let previous = self.representation.clone();
+        for (y, row) in self.representation.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let moved: u8 = match source(x, y) {
+                    Some((from_x, from_y)) => previous[from_y][from_x] & mask,
+                    None => 0,
+                };
+                *pixel = (*pixel & !mask) | moved;
+            }
+        }
+        self.changed = true;
+    }
+
+    // flips must match the current width and height, plane is the single plane bit being drawn to
+    pub fn draw(&mut self, flips: Vec<Vec<bool>>, plane: usize) -> bool {
+        let plane: u8 = plane as u8;
         let mut carry: bool = false;
         for (y, row) in flips.iter().enumerate() {
             for (x, &col) in row.iter().enumerate() {
-                let canvas_x = x as u32 * SCALE_FACTOR;
-                let canvas_y = y as u32 * SCALE_FACTOR;
-
-                self.representation[y][x] ^= col;
-                if !carry && col && !self.representation[y][x] {
+                if !col {
+                    continue;
+                }
+                if self.representation[y][x] & plane != 0 {
                     carry = true;
                 }
-
-                self.canvas.set_draw_color(color(self.representation[y][x]));
-                let _ = self.canvas
-                    .fill_rect(Rect::new(canvas_x as i32, canvas_y as i32, SCALE_FACTOR, SCALE_FACTOR));
+                self.representation[y][x] ^= plane;
             }
         }
-        self.canvas.present(); // might need to move this to its own function that fires in the loop
+        self.changed = true;
 
         return carry;
     }
-}
 
-fn color(value: bool) -> pixels::Color {
-    if value {
-        pixels::Color::RGB(0, 250, 0)
-    } else {
-        pixels::Color::RGB(0, 0, 0)
+    // put back a framebuffer taken from rows(), size and all
+    pub fn restore(&mut self, rows: Vec<Vec<u8>>, selected_planes: usize) {
+        self.height = rows.len();
+        self.width = rows[0].len();
+        self.representation = rows;
+        self.selected_planes = selected_planes;
+        self.changed = true;
+    }
+
+    pub fn rows(&self) -> &[Vec<u8>] {
+        return &self.representation;
     }
-}
\ No newline at end of file
+
+    // whether the framebuffer changed since the last call
+    pub fn take_changed(&mut self) -> bool {
+        let changed = self.changed;
+        self.changed = false;
+        return changed;
+    }
+}
@@ -0,0 +1,42 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    UnknownOpcode,
+    MachineRoutine, // 0nnn, there's no 1802 to run it on
+    EscapedProgram, // ran into zeroed memory, usually a missing jump at the end of a program
+    StackOverflow,
+    StackUnderflow,
+    ProgramCounterOutOfBounds,
+    MemoryOutOfBounds(usize),
+}
+
+// what went wrong, and where
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionError {
+    pub pc: usize,
+    pub opcode: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            ErrorKind::MachineRoutine => write!(f, "unsupported 0NNN machine language routine"),
+            ErrorKind::EscapedProgram => write!(f, "escaped program in memory"),
+            ErrorKind::StackOverflow => write!(f, "stack overflow"),
+            ErrorKind::StackUnderflow => write!(f, "return with an empty stack"),
+            ErrorKind::ProgramCounterOutOfBounds => write!(f, "program counter ran off the end of memory"),
+            ErrorKind::MemoryOutOfBounds(address) => write!(f, "memory access out of bounds at {:#06x}", address),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (opcode {:04X} at {:#06x})", self.kind, self.opcode, self.pc)
+    }
+}
+
+impl std::error::Error for ExecutionError {}
@@ -1,55 +1,89 @@
-extern crate sdl2;
-extern crate keyboard_query;
-
-mod storage;
-mod display;
-mod audio;
+pub mod storage;
+pub mod display;
+pub mod error;
+pub mod snapshot;
+pub mod quirks;
+pub mod clock;
+pub mod timing;
+pub mod random;
 mod operators;
 
-use keyboard_query::{DeviceState, DeviceQuery};
-use storage::{instruction::Instruction, Storage};
+use storage::{instruction::Instruction, Storage, MEM_SIZE, XO_MEM_SIZE};
 use operators::*;
 use display::Display;
-use audio::Audio;
+use error::{ErrorKind, ExecutionError};
+use quirks::Quirks;
+use clock::Clock;
+use timing::{Timing, vip_cycles, VIP_PROGRAM_CYCLES};
 
-use std::time::{Duration, Instant};
-use std::thread::sleep;
-use std::io::{stdin, stdout, Read, Write};
+use random::Random;
+use rand::Rng;
+use std::fs;
+use std::path::Path;
 
 const OPCODE_INITIAL_CASES: usize = 16;
-const CALC_PER_FRAME: usize = 12;
-const MIN_CLOCK_TIME: u32 = 1388888; // in nanos
-const DEBUG: bool = true;
+type OpcodeHandler = fn(&mut Runtime, Instruction) -> Result<(), ErrorKind>;
+pub const CALC_PER_FRAME: usize = 12; // at the default clock rate
+pub const KEYBOARD_SIZE: usize = 16;
+pub const AUDIO_PATTERN_SIZE: usize = 16; // x0-chip 128 bit sample buffer
+const DEFAULT_PITCH: usize = 64; // plays the pattern at 4000hz
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Mode {
     CHIP8,
     SCHIP,
     X0CHIP,
 }
 
-fn pause() {
-    let mut stdout = stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
-    stdout.flush().unwrap();
-    stdin().read(&mut [0]).unwrap();
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum State {
+    Running,
+    Exited, // the program ran 00fd
+    Faulted(ExecutionError), // stopped on a bad instruction, with everything left as it was for inspection
 }
+
+// the machine itself, with no window, sound device or keyboard attached
 pub struct Runtime {
     pub mode: Mode,
+    pub quirks: Quirks,
+    pub clock: Clock,
+    pub timing: Timing,
+    budget: isize, // instructions or vip cycles left this frame, overspending carries into the next one
     pub storage: Storage,
     pub display: Display,
-    pub audio: Audio,
-    opcode_handlers: [fn(&mut Runtime, Instruction); OPCODE_INITIAL_CASES],
+    opcode_handlers: [OpcodeHandler; OPCODE_INITIAL_CASES],
+    pub state: State,
     pub delay_timer: usize,
     pub sound_timer: usize,
-    pub device_state: DeviceState,
-    pub current_key_press: Option<u16>,
+    pub audio_pattern: [usize; AUDIO_PATTERN_SIZE],
+    pub pattern_loaded: bool, // the buzzer plays the pattern once the program's given it one, a plain tone before
+    pub pitch: usize,
+    pub keys: [bool; KEYBOARD_SIZE], // held state of the hex keypad, set by whoever owns the input
+    pub pressed_this_frame: [bool; KEYBOARD_SIZE], // edges since clear_key_edges, so taps shorter than a frame still count
+    pub released_this_frame: [bool; KEYBOARD_SIZE],
+    pub current_key_press: Option<usize>,
+    pub waiting_for_vblank: bool, // a sprite was drawn with the display wait quirk on, nothing runs until the next tick
+    pub trace: bool, // print every instruction to stderr as it runs
+    seed: u64,
+    pub random: Random, // what cxnn draws from, the same seed gives the same numbers
 }
 
 impl Runtime {
     pub fn initialize(file_name: String, mode: Mode) -> Runtime {
-        let sdl_context = sdl2::init().unwrap();
-        let opcode_handlers: [fn(&mut Runtime, Instruction); OPCODE_INITIAL_CASES] = [
+        let filepath = Path::new(&file_name);
+        assert!(filepath.exists(), "{:#?}", filepath.display());
+
+        let contents: Vec<u8> = fs::read(
+            filepath,
+        ).unwrap_or_else(
+            |_| panic!("path {} to file not found", filepath.display()),
+        );
+
+        return Runtime::new(&contents, mode);
+    }
+
+    pub fn new(program: &[u8], mode: Mode) -> Runtime {
+        let opcode_handlers: [OpcodeHandler; OPCODE_INITIAL_CASES] = [
             handle0,
             handle1,
             handle2,
@@ -67,42 +101,141 @@ impl Runtime {
             handleE,
             handleF,
         ];
-        let storage: Storage = Storage::initialize(file_name);
-        let display: Display = Display::initialize(&sdl_context);
-        let audio: Audio = Audio::initialize(&sdl_context);
-        let device_state: DeviceState = DeviceState::new();
+        let memory_size: usize = if mode == Mode::X0CHIP { XO_MEM_SIZE } else { MEM_SIZE };
+        let storage: Storage = Storage::initialize(program, memory_size);
+        let display: Display = Display::initialize();
+        let seed: u64 = rand::thread_rng().gen();
         return Runtime {
             mode,
+            quirks: Quirks::for_mode(mode),
+            clock: Clock::default(),
+            timing: Timing::Instructions,
+            budget: 0,
             storage,
             display,
-            audio,
             opcode_handlers,
+            state: State::Running,
             delay_timer: 0,
             sound_timer: 0,
-            device_state,
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            pattern_loaded: false,
+            pitch: DEFAULT_PITCH,
+            keys: [false; KEYBOARD_SIZE],
+            pressed_this_frame: [false; KEYBOARD_SIZE],
+            released_this_frame: [false; KEYBOARD_SIZE],
             current_key_press: None,
+            waiting_for_vblank: false,
+            trace: false,
+            seed,
+            random: Random::seeded(seed),
         };
     }
 
-    pub fn frame(&mut self) {
-        for _i in 0..CALC_PER_FRAME {
-            let start: Instant = Instant::now();
-            let instruction: Instruction = self.storage.get_instruction();
-            if DEBUG { println!("{:?}", instruction); }//pause(); }
-            self.opcode_handlers[instruction.identifier](self, instruction);
-            let calculation_time = Instant::now().duration_since(start);
-            sleep(calculation_time.checked_sub(Duration::new(0, MIN_CLOCK_TIME)).unwrap_or_default());
+    pub fn is_halted(&self) -> bool {
+        return self.state != State::Running;
+    }
+
+    // fetch, decode and execute a single instruction
+    // a fault leaves the pc on the offending instruction and halts the machine until it's reset
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        match self.state {
+            State::Running => {},
+            State::Exited => return Ok(()),
+            State::Faulted(error) => return Err(error),
+        }
+        if self.waiting_for_vblank {
+            return Ok(());
         }
+        let pc: usize = self.storage.program_counter;
+        let (opcode, result) = match self.storage.get_instruction() {
+            Ok(instruction) => {
+                if self.trace { eprintln!("{:#06x}: {:?}", pc, instruction); }
+                let opcode: usize = instruction.raw;
+                self.budget -= match self.timing {
+                    Timing::Instructions => 1,
+                    Timing::Vip => vip_cycles(&instruction) as isize,
+                };
+                (opcode, self.opcode_handlers[instruction.identifier](self, instruction))
+            },
+            Err(kind) => (0, Err(kind)),
+        };
+        if let Err(kind) = result {
+            let error = ExecutionError { pc, opcode, kind };
+            self.storage.program_counter = pc;
+            self.state = State::Faulted(error);
+            return Err(error);
+        }
+        return Ok(());
+    }
 
+    // count both timers down, meant to be called at 60hz. this is the vip's vblank interrupt,
+    // so it's also what releases a display wait
+    pub fn tick_timers(&mut self) {
+        self.waiting_for_vblank = false;
+        self.random.vblank();
         if self.sound_timer > 0 {
-            self.audio.start_beep();
             self.sound_timer -= 1;
-        } else {
-            self.audio.stop_beep();
         }
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
     }
-}
 
+    // top up the budget for a new frame. leftovers from a frame cut short by a display wait are dropped,
+    // but an instruction that ran past the end of the last frame is paid for out of this one
+    pub fn start_frame(&mut self) {
+        let allowance = match self.timing {
+            Timing::Instructions => self.clock.next_frame(),
+            Timing::Vip => VIP_PROGRAM_CYCLES,
+        };
+        self.budget = self.budget.min(0) + allowance as isize;
+    }
+
+    // whether there's anything left to run before the next timer tick
+    pub fn frame_running(&self) -> bool {
+        return self.budget > 0 && !self.waiting_for_vblank && !self.is_halted();
+    }
+
+    // a whole frame's worth of instructions followed by a timer tick, as fast as possible
+    pub fn frame(&mut self) -> Result<(), ExecutionError> {
+        self.start_frame();
+        while self.frame_running() {
+            self.step()?;
+        }
+        self.tick_timers();
+        return Ok(());
+    }
+
+    // press or release a hex key, noting the edge for this frame
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        let key = key & 0x0F;
+        if pressed && !self.keys[key] {
+            self.pressed_this_frame[key] = true;
+        }
+        if !pressed && self.keys[key] {
+            self.released_this_frame[key] = true;
+        }
+        self.keys[key] = pressed;
+    }
+
+    // forget the last frame's presses and releases, before feeding in the next frame's input
+    pub fn clear_key_edges(&mut self) {
+        self.pressed_this_frame = [false; KEYBOARD_SIZE];
+        self.released_this_frame = [false; KEYBOARD_SIZE];
+    }
+
+    // what the random numbers were started from, enough to get the same ones again
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    // start the random numbers over from a seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.random.reseed(seed);
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        return self.sound_timer > 0;
+    }
+}
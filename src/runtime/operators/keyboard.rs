@@ -1,21 +0,0 @@
-const KEYBOARD_SIZE: usize = 16;
-
-// for use with keyboard_query
-pub const KEY_MAP: [u16; KEYBOARD_SIZE] = [
-    45,
-    2,
-    3,
-    4,
-    16,
-    17,
-    18,
-    30,
-    31,
-    32,
-    44,
-    46,
-    5,
-    19,
-    33,
-    47,
-];
\ No newline at end of file
@@ -1,161 +1,255 @@
-extern crate rand;
-
-mod keyboard;
-
-use crate::runtime::{Runtime, Instruction, display::{CHIP8_WIDTH, CHIP8_HEIGHT}, DeviceQuery, Mode};
-use rand::Rng;
-use std::collections::HashSet;
+use crate::runtime::{Runtime, Instruction, State, storage::{SCHIP_NUM_FLAGS, NUM_FLAGS, XO_MEM_SIZE}, display::{NUM_PLANES, CHIP8_WIDTH}, Mode, AUDIO_PATTERN_SIZE, KEYBOARD_SIZE};
+use crate::runtime::error::ErrorKind;
+use crate::runtime::timing::Timing;
 
 const VARIABLE_MODULUS: usize = 256; // the max value settable to a variable
 const BIT_LENGTH: usize = 8; // the number of bits in an element of memory
+const BIG_SPRITE_SIZE: usize = 16; // schip dxy0 sprites are 16x16
+const SCROLL_SIDEWAYS: usize = 4; // schip scrolls left and right by 4 pixels
+
+#[cfg(test)]
+mod tests;
+
+// i plus an offset. x0-chip's i is 16 bits and wraps round its 64k of memory the way octo's does,
+// everywhere else running off the end of memory is a fault
+fn index_address(runtime: &Runtime, offset: usize) -> usize {
+    let address = runtime.storage.index_register + offset;
+    if runtime.mode == Mode::X0CHIP {
+        return address % XO_MEM_SIZE;
+    }
+    return address;
+}
 
 // function for function map array
-fn handle_error_case(_runtime: &mut Runtime, instruction: Instruction) {
-    panic!("{:?} has unhandled opcode case", instruction);
+fn handle_error_case(_runtime: &mut Runtime, _instruction: Instruction) -> Result<(), ErrorKind> {
+    return Err(ErrorKind::UnknownOpcode);
 }
 
-// clear screen or pop stacked instruction
-pub fn handle0(runtime: &mut Runtime, instruction: Instruction) {
+// clear screen or pop stacked instruction, plus the schip screen controls
+pub fn handle0(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     // clear screen
     if instruction.nnn == 0x0E0 {
         runtime.display.clear();
-        return;
+        return Ok(());
     }
     // pop stacked instruction
     if instruction.nnn == 0x0EE {
-        runtime.storage.pop_pc_from_stack();
-        return;
+        return runtime.storage.pop_pc_from_stack();
+    }
+    if runtime.mode != Mode::CHIP8 {
+        // scroll down n rows
+        if instruction.nnn & 0xFF0 == 0x0C0 {
+            runtime.display.scroll_down(instruction.n);
+            return Ok(());
+        }
+        // scroll up n rows
+        if instruction.nnn & 0xFF0 == 0x0D0 && runtime.mode == Mode::X0CHIP {
+            runtime.display.scroll_up(instruction.n);
+            return Ok(());
+        }
+        match instruction.nnn {
+            0x0FB => {
+                runtime.display.scroll_right(SCROLL_SIDEWAYS);
+                return Ok(());
+            },
+            0x0FC => {
+                runtime.display.scroll_left(SCROLL_SIDEWAYS);
+                return Ok(());
+            },
+            0x0FD => {
+                runtime.state = State::Exited;
+                return Ok(());
+            },
+            0x0FE => {
+                runtime.display.set_hires(false);
+                return Ok(());
+            },
+            0x0FF => {
+                runtime.display.set_hires(true);
+                return Ok(());
+            },
+            _ => {},
+        }
     }
     if instruction.nnn == 0 {
-        panic!("escaped program in memory");
+        return Err(ErrorKind::EscapedProgram);
     }
-    panic!("unsupported program with 0NNN: Execute machine language routine opcode");
+    return Err(ErrorKind::MachineRoutine);
 }
 
 // jump to nnn
-pub fn handle1(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle1(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.program_counter = instruction.nnn;
+    return Ok(());
 }
 
 // jump to nnn and add current PC to stack
-pub fn handle2(runtime: &mut Runtime, instruction: Instruction) {
-    runtime.storage.add_pc_to_stack();
+pub fn handle2(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.storage.add_pc_to_stack()?;
     runtime.storage.program_counter = instruction.nnn;
+    return Ok(());
 }
 
 // skip if vx == nn
-pub fn handle3(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle3(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     if runtime.storage.variables[instruction.x] == instruction.nn {
-        runtime.storage.program_counter += 2;
+        runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
     }
+    return Ok(());
 }
 
 // skip if vx != nn
-pub fn handle4(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle4(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     if runtime.storage.variables[instruction.x] != instruction.nn {
-        runtime.storage.program_counter += 2;
+        runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
     }
+    return Ok(());
 }
 
 // skip if vx == vy
-pub fn handle5(runtime: &mut Runtime, instruction: Instruction) {
+fn handle5XY0(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     if runtime.storage.variables[instruction.x] == runtime.storage.variables[instruction.y] {
-        runtime.storage.program_counter += 2;
+        runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
+    }
+    return Ok(());
+}
+
+// the registers from vx to vy, in either direction
+fn register_range(instruction: &Instruction) -> Vec<usize> {
+    if instruction.x <= instruction.y {
+        return (instruction.x..=instruction.y).collect();
+    }
+    return (instruction.y..=instruction.x).rev().collect();
+}
+
+// store vx to vy into memory starting with the index register, leaving it unchanged
+fn handle5XY2(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    for (offset, register) in register_range(&instruction).into_iter().enumerate() {
+        runtime.storage.write(index_address(runtime, offset), runtime.storage.variables[register])?;
+    }
+    return Ok(());
+}
+
+// load vx to vy from memory starting with the index register, leaving it unchanged
+fn handle5XY3(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    for (offset, register) in register_range(&instruction).into_iter().enumerate() {
+        runtime.storage.variables[register] = runtime.storage.read(index_address(runtime, offset))?;
+    }
+    return Ok(());
+}
+
+// branching for the 5 opcode cases
+pub fn handle5(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    match instruction.n {
+        0x0 => handle5XY0(runtime, instruction),
+        0x2 if runtime.mode == Mode::X0CHIP => handle5XY2(runtime, instruction),
+        0x3 if runtime.mode == Mode::X0CHIP => handle5XY3(runtime, instruction),
+        _ => handle_error_case(runtime, instruction),
     }
 }
 
 // set variable register
-pub fn handle6(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle6(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] = instruction.nn;
+    return Ok(());
 }
 
 // add to variable register
-pub fn handle7(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle7(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] += instruction.nn;
     runtime.storage.variables[instruction.x] %= VARIABLE_MODULUS;
+    return Ok(());
 }
 
 // set vx to vy
-fn handle8XY0(runtime: &mut Runtime, instruction: Instruction) {
+fn handle8XY0(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] = runtime.storage.variables[instruction.y];
+    return Ok(());
 }
 
 // set vx to vx | vy
-fn handle8XY1(runtime: &mut Runtime, instruction: Instruction) {
+fn handle8XY1(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] |= runtime.storage.variables[instruction.y];
-    if runtime.mode == Mode::CHIP8 {
+    if runtime.quirks.vf_reset {
         runtime.storage.variables[0x0F] = 0;
     }
+    return Ok(());
 }
 
 // set vx to vx & vy
-fn handle8XY2(runtime: &mut Runtime, instruction: Instruction) {
+fn handle8XY2(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] &= runtime.storage.variables[instruction.y];
-    if runtime.mode == Mode::CHIP8 {
+    if runtime.quirks.vf_reset {
         runtime.storage.variables[0x0F] = 0;
     }
+    return Ok(());
 }
 
 // set vx to vx ^ vy
-fn handle8XY3(runtime: &mut Runtime, instruction: Instruction) {
+fn handle8XY3(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] ^= runtime.storage.variables[instruction.y];
-    if runtime.mode == Mode::CHIP8 {
+    if runtime.quirks.vf_reset {
         runtime.storage.variables[0x0F] = 0;
     }
+    return Ok(());
+}
+
+// write an arithmetic result then its vf flag, so the flag wins when vf is the target
+fn set_with_flag(runtime: &mut Runtime, x: usize, result: usize, flag: usize) {
+    runtime.storage.variables[x] = result;
+    runtime.storage.variables[0x0F] = flag;
+}
+
+// the register 8xy6 and 8xye shift, vx in place or a copy of vy
+fn shift_source(runtime: &Runtime, instruction: &Instruction) -> usize {
+    if runtime.quirks.shifting {
+        return runtime.storage.variables[instruction.x];
+    }
+    return runtime.storage.variables[instruction.y];
 }
 
 // set vx to vx + vy with carry on overflow
-fn handle8XY4(runtime: &mut Runtime, instruction: Instruction) {
-    runtime.storage.variables[instruction.x] += runtime.storage.variables[instruction.y];
-    runtime.storage.variables[0x0F] = if runtime.storage.variables[instruction.x] > VARIABLE_MODULUS { 1 } else { 0 };
-    runtime.storage.variables[instruction.x] %= VARIABLE_MODULUS;
+fn handle8XY4(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let sum = runtime.storage.variables[instruction.x] + runtime.storage.variables[instruction.y];
+    let carry = if sum >= VARIABLE_MODULUS { 1 } else { 0 };
+    set_with_flag(runtime, instruction.x, sum % VARIABLE_MODULUS, carry);
+    return Ok(());
 }
 
 // set vx to vx - vy with carry on LACK of underflow
-fn handle8XY5(runtime: &mut Runtime, instruction: Instruction) {
-    let carry: usize = if runtime.storage.variables[instruction.y] < runtime.storage.variables[instruction.x] { 1 } else { 0 };
-    runtime.storage.variables[instruction.x] = if carry == 1 {
-        runtime.storage.variables[instruction.x] - runtime.storage.variables[instruction.y]
-    } else {
-        runtime.storage.variables[instruction.x] + VARIABLE_MODULUS - runtime.storage.variables[instruction.y]
-    };
-    runtime.storage.variables[0x0F] = carry;
+fn handle8XY5(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let (vx, vy) = (runtime.storage.variables[instruction.x], runtime.storage.variables[instruction.y]);
+    let carry: usize = if vy <= vx { 1 } else { 0 };
+    set_with_flag(runtime, instruction.x, (vx + VARIABLE_MODULUS - vy) % VARIABLE_MODULUS, carry);
+    return Ok(());
 }
 
-// right shift vx with carry for underflow
-fn handle8XY6(runtime: &mut Runtime, instruction: Instruction) {
-    if runtime.mode != Mode::SCHIP {
-        runtime.storage.variables[instruction.x] = runtime.storage.variables[instruction.y];
-    }
-    let carry = runtime.storage.variables[instruction.x] & 1; // grab lowest bit that'll be shifted out
-    runtime.storage.variables[instruction.x] >>= 1;
-    runtime.storage.variables[0x0F] = carry;
+// right shift with carry for underflow
+fn handle8XY6(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let source = shift_source(runtime, &instruction);
+    let carry = source & 1; // grab lowest bit that'll be shifted out
+    set_with_flag(runtime, instruction.x, source >> 1, carry);
+    return Ok(());
 }
 
 // set vx to vy - vx with carry on LACK of underflow
-fn handle8XY7(runtime: &mut Runtime, instruction: Instruction) {
-    let carry = if runtime.storage.variables[instruction.x] < runtime.storage.variables[instruction.y] { 1 } else { 0 };
-    runtime.storage.variables[instruction.x] = if carry == 1 {
-        runtime.storage.variables[instruction.y] - runtime.storage.variables[instruction.x]
-    } else {
-        runtime.storage.variables[instruction.y] + VARIABLE_MODULUS - runtime.storage.variables[instruction.x]
-    };
-    runtime.storage.variables[0x0F] = carry;
+fn handle8XY7(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let (vx, vy) = (runtime.storage.variables[instruction.x], runtime.storage.variables[instruction.y]);
+    let carry: usize = if vx <= vy { 1 } else { 0 };
+    set_with_flag(runtime, instruction.x, (vy + VARIABLE_MODULUS - vx) % VARIABLE_MODULUS, carry);
+    return Ok(());
 }
 
-// left shift vx with carry for overflow
-fn handle8XYE(runtime: &mut Runtime, instruction: Instruction) {
-    if runtime.mode != Mode::SCHIP {
-        runtime.storage.variables[instruction.x] = runtime.storage.variables[instruction.y];
-    }
-    let carry = (runtime.storage.variables[instruction.x] >> (BIT_LENGTH - 1)) & 1; // grab highest bit that'll be shifted out
-    runtime.storage.variables[instruction.x] <<= 1;
-    runtime.storage.variables[instruction.x] %= VARIABLE_MODULUS;
-    runtime.storage.variables[0x0F] = carry;
+// left shift with carry for overflow
+fn handle8XYE(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let source = shift_source(runtime, &instruction);
+    let carry = (source >> (BIT_LENGTH - 1)) & 1; // grab highest bit that'll be shifted out
+    set_with_flag(runtime, instruction.x, (source << 1) % VARIABLE_MODULUS, carry);
+    return Ok(());
 }
 
 // branching for several 8 opcode cases
-pub fn handle8(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle8(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     match instruction.n {
         0x0 => handle8XY0(runtime, instruction),
         0x1 => handle8XY1(runtime, instruction),
@@ -171,183 +265,284 @@ pub fn handle8(runtime: &mut Runtime, instruction: Instruction) {
 }
 
 // skip if vx != vy
-pub fn handle9(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handle9(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     if runtime.storage.variables[instruction.x] != runtime.storage.variables[instruction.y] {
-        runtime.storage.program_counter += 2;
+        runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
     }
+    return Ok(());
 }
 
 // set index register
-pub fn handleA(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handleA(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.index_register = instruction.nnn;
+    return Ok(());
 }
 
-// jump to nnn + v0
-pub fn handleB(runtime: &mut Runtime, instruction: Instruction) {
-    let jump_increment = if runtime.mode == Mode::SCHIP {
+// jump to nnn + v0, or xnn + vx with the jumping quirk
+pub fn handleB(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let jump_increment = if runtime.quirks.jumping {
         runtime.storage.variables[instruction.x]
     } else {
         runtime.storage.variables[0]
     };
     runtime.storage.program_counter = instruction.nnn + jump_increment;
+    return Ok(());
 }
 
 // set vx to nn & a random number
-pub fn handleC(runtime: &mut Runtime, instruction: Instruction) {
-    let random: u16 = rand::thread_rng().gen();
+pub fn handleC(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let random: u8 = runtime.random.next(&runtime.storage.memory);
     runtime.storage.variables[instruction.x] = (random as usize) & instruction.nn;
+    return Ok(());
 }
 
 
-// draw sprites to screen
-pub fn handleD(runtime: &mut Runtime, instruction: Instruction) {
-    let vx = runtime.storage.variables[instruction.x] % CHIP8_WIDTH;
-    let vy = runtime.storage.variables[instruction.y] % CHIP8_HEIGHT;
+// draw sprites to screen, dxy0 draws a 16x16 sprite outside of chip-8
+pub fn handleD(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let width = runtime.display.width;
+    let height = runtime.display.height;
+    let vx = runtime.storage.variables[instruction.x] % width;
+    let vy = runtime.storage.variables[instruction.y] % height;
     runtime.storage.variables[0x0F] = 0;
-    let index = runtime.storage.index_register;
 
-    let mut new_flips: [[bool; CHIP8_WIDTH]; CHIP8_HEIGHT] = [[false; CHIP8_WIDTH]; CHIP8_HEIGHT];
+    let (sprite_width, sprite_height) = if instruction.n == 0 && runtime.mode != Mode::CHIP8 {
+        (BIG_SPRITE_SIZE, BIG_SPRITE_SIZE)
+    } else {
+        (BIT_LENGTH, instruction.n)
+    };
+    let bytes_per_row = sprite_width / BIT_LENGTH;
 
     // only do rows that stay on the screen
-    let imax: usize = if vy + instruction.n > CHIP8_HEIGHT && runtime.mode != Mode::X0CHIP {
-        CHIP8_HEIGHT - vy
+    let imax: usize = if vy + sprite_height > height && runtime.quirks.clipping {
+        height - vy
     } else {
-        instruction.n
+        sprite_height
     };
 
     // only draw the sprite in the amount of the row left on the screen
-    let jmax: usize = if vx + BIT_LENGTH > CHIP8_WIDTH && runtime.mode != Mode::X0CHIP {
-        CHIP8_WIDTH - vx
+    let jmax: usize = if vx + sprite_width > width && runtime.quirks.clipping {
+        width - vx
     } else {
-        BIT_LENGTH
+        sprite_width
     };
 
-    for i in 0..imax {
-        let sprite = runtime.storage.memory[index + i];
+    // each selected plane takes the next sprite's worth of memory
+    let mut sprite_start: usize = 0;
+    for plane_index in 0..NUM_PLANES {
+        let plane: usize = 1 << plane_index;
+        if runtime.display.selected_planes & plane == 0 {
+            continue;
+        }
+        let mut new_flips: Vec<Vec<bool>> = vec![vec![false; width]; height];
 
-        for j in 0..jmax {
-            new_flips[(vy + i) % CHIP8_HEIGHT][(vx + j) % CHIP8_WIDTH] = sprite & (1 << (BIT_LENGTH - 1 - j)) > 0;
+        for i in 0..imax {
+            let mut sprite: usize = 0;
+            for byte in 0..bytes_per_row {
+                sprite = (sprite << BIT_LENGTH) | runtime.storage.read(index_address(runtime, sprite_start + i * bytes_per_row + byte))?;
+            }
+
+            for j in 0..jmax {
+                new_flips[(vy + i) % height][(vx + j) % width] = sprite & (1 << (sprite_width - 1 - j)) > 0;
+            }
         }
-    }
 
-    if runtime.display.draw(new_flips) {
-        runtime.storage.variables[0x0F] = 1;
+        if runtime.display.draw(new_flips, plane) {
+            runtime.storage.variables[0x0F] = 1;
+        }
+        sprite_start += sprite_height * bytes_per_row;
     }
+    // only lores ever waited, schip 1.1 drew hires sprites straight away
+    let lores = runtime.display.width == CHIP8_WIDTH;
+    if (runtime.quirks.display_wait && lores) || runtime.timing == Timing::Vip {
+        runtime.waiting_for_vblank = true;
+    }
+    return Ok(());
 }
 
 // skip if key pressed/notpressed
-pub fn handleE(runtime: &mut Runtime, instruction: Instruction) {
-    let target_key = keyboard::KEY_MAP[runtime.storage.variables[instruction.x]];
-    let keys = runtime.device_state.get_keys();
+pub fn handleE(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    let target_key_pressed = runtime.keys[runtime.storage.variables[instruction.x] & 0x0F];
     if instruction.nn == 0x9E {
-        if keys.contains(&target_key) {
-            runtime.storage.program_counter += 2;
+        if target_key_pressed {
+            runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
         }
-        return;
+        return Ok(());
     }
     if instruction.nn == 0xA1 {
-        if !keys.contains(&(target_key as u16)) {
-            runtime.storage.program_counter += 2;
+        if !target_key_pressed {
+            runtime.storage.skip_instruction(runtime.mode == Mode::X0CHIP);
         }
-        return;
+        return Ok(());
     }
-    panic!("bad E opcode nn: {:?}", instruction);
+    return Err(ErrorKind::UnknownOpcode);
 }
 
 // set vx to the delay timer value
-fn handleFX07(runtime: &mut Runtime, instruction: Instruction) {
+fn handleFX07(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.variables[instruction.x] = runtime.delay_timer;
+    return Ok(());
 }
 
-// get keypress to vx
-fn handleFX0A(runtime: &mut Runtime, instruction: Instruction) {
-    let pressed_keys = runtime.device_state.get_keys();
-    let pressed_key_set = pressed_keys.iter().collect::<HashSet<_>>();
-    let key_set_ref = keyboard::KEY_MAP.iter().collect::<HashSet<_>>();
-    let pressed_device_keys: Vec<&&u16> = pressed_key_set.intersection(&key_set_ref).collect();
-    
-    if runtime.current_key_press.is_some() && !pressed_device_keys.contains(&&&runtime.current_key_press.unwrap()) {
-        let key_value = keyboard::KEY_MAP.iter().position(|e| *e==runtime.current_key_press.unwrap()).unwrap();
-        runtime.storage.variables[instruction.x] = key_value;
-        runtime.current_key_press = None;
-        return;
-    }
-    if runtime.current_key_press.is_none() && pressed_device_keys.len() != 0 {
-        let found_key = **pressed_device_keys[0];
-        runtime.current_key_press = Some(found_key);
+// get keypress to vx, which only lands once the key is released. going by this frame's edges as well as
+// what's held means a key tapped within a single frame still counts
+fn handleFX0A(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    if let Some(key) = runtime.current_key_press {
+        if !runtime.keys[key] {
+            runtime.storage.variables[instruction.x] = key;
+            runtime.current_key_press = None;
+            return Ok(());
+        }
+    } else {
+        runtime.current_key_press = (0..KEYBOARD_SIZE).find(|&key| runtime.keys[key] || runtime.pressed_this_frame[key]);
     }
     runtime.storage.program_counter -= 2;
+    return Ok(());
 }
 
 // set the delay timer value to vx
-fn handleFX15(runtime: &mut Runtime, instruction: Instruction) {
+fn handleFX15(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.delay_timer = runtime.storage.variables[instruction.x];
+    return Ok(());
 }
 
 // set the sound timer value to vx
-fn handleFX18(runtime: &mut Runtime, instruction: Instruction) {
+fn handleFX18(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.sound_timer = runtime.storage.variables[instruction.x];
+    return Ok(());
 }
 
-// add vx it index register
-fn handleFX1E(runtime: &mut Runtime, instruction: Instruction) {
-    runtime.storage.index_register += runtime.storage.variables[instruction.x];
-    if runtime.storage.index_register > 0x0FFF {
+// add vx to index register. going past 12 bits sets vf, except in x0-chip where those addresses are ordinary
+fn handleFX1E(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.storage.index_register = index_address(runtime, runtime.storage.variables[instruction.x]);
+    if runtime.storage.index_register > 0x0FFF && runtime.mode != Mode::X0CHIP {
         runtime.storage.variables[0x0F] = 1;
     }
+    return Ok(());
 }
 
 // set index register to font of vx
-fn handleFX29(runtime: &mut Runtime, instruction: Instruction) {
+fn handleFX29(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     runtime.storage.index_register = runtime.storage.get_font_item_location(runtime.storage.variables[instruction.x]);
+    return Ok(());
+}
+
+// set index register to the large schip font of vx
+fn handleFX30(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.storage.index_register = runtime.storage.get_big_font_item_location(runtime.storage.variables[instruction.x]);
+    return Ok(());
 }
 
 // decimal conversion of vx into memory starting with the index register
-fn handleFX33(runtime: &mut Runtime, instruction: Instruction) {
+fn handleFX33(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     let vx = runtime.storage.variables[instruction.x];
     let hundreds = (vx / 100) % 10;
     let tens = (vx / 10) % 10;
     let ones = vx % 10;
-    let start_address = runtime.storage.index_register;
-    runtime.storage.memory[start_address] = hundreds;
-    runtime.storage.memory[start_address + 1] = tens;
-    runtime.storage.memory[start_address + 2] = ones;
+    runtime.storage.write(index_address(runtime, 0), hundreds)?;
+    runtime.storage.write(index_address(runtime, 1), tens)?;
+    runtime.storage.write(index_address(runtime, 2), ones)?;
+    return Ok(());
 }
 
 // store v0 to vx into memory starting with the index register
-fn handleFX55(runtime: &mut Runtime, instruction: Instruction) {
-    let start_address = runtime.storage.index_register;
+fn handleFX55(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     for i in 0..(instruction.x+1) {
-        runtime.storage.memory[start_address + i] = runtime.storage.variables[i];
+        runtime.storage.write(index_address(runtime, i), runtime.storage.variables[i])?;
     }
-    if runtime.mode == Mode::CHIP8 || runtime.mode == Mode::X0CHIP {
-        runtime.storage.index_register += instruction.x + 1;
+    if runtime.quirks.memory_increment {
+        runtime.storage.index_register = index_address(runtime, instruction.x + 1);
     }
+    return Ok(());
 }
 
 // load v0 to vx from memory starting with the index register
-fn handleFX65(runtime: &mut Runtime, instruction: Instruction) {
-    let start_address = runtime.storage.index_register;
+fn handleFX65(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     for i in 0..(instruction.x+1) {
-        runtime.storage.variables[i] = runtime.storage.memory[start_address + i];
+        runtime.storage.variables[i] = runtime.storage.read(index_address(runtime, i))?;
     }
-    if runtime.mode == Mode::CHIP8 || runtime.mode == Mode::X0CHIP {
-        runtime.storage.index_register += instruction.x + 1;
+    if runtime.quirks.memory_increment {
+        runtime.storage.index_register = index_address(runtime, instruction.x + 1);
     }
+    return Ok(());
+}
+
+// schip only has half as many flags as x0-chip
+fn num_flags(runtime: &Runtime) -> usize {
+    if runtime.mode == Mode::X0CHIP {
+        return NUM_FLAGS;
+    }
+    return SCHIP_NUM_FLAGS;
+}
+
+// store v0 to vx into the rpl user flags
+fn handleFX75(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    if instruction.x >= num_flags(runtime) {
+        return Err(ErrorKind::UnknownOpcode);
+    }
+    for i in 0..(instruction.x+1) {
+        runtime.storage.flags[i] = runtime.storage.variables[i];
+    }
+    return Ok(());
+}
+
+// load v0 to vx from the rpl user flags
+fn handleFX85(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    if instruction.x >= num_flags(runtime) {
+        return Err(ErrorKind::UnknownOpcode);
+    }
+    for i in 0..(instruction.x+1) {
+        runtime.storage.variables[i] = runtime.storage.flags[i];
+    }
+    return Ok(());
+}
+
+// set index register to the 16 bit address following the instruction
+fn handleF000(runtime: &mut Runtime, _instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.storage.index_register = runtime.storage.get_word(runtime.storage.program_counter)?;
+    runtime.storage.program_counter += 2;
+    return Ok(());
+}
+
+// select the drawing planes from the x nibble
+fn handleFN01(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.display.selected_planes = instruction.x & ((1 << NUM_PLANES) - 1);
+    return Ok(());
+}
+
+// load the audio pattern buffer from memory starting with the index register
+fn handleF002(runtime: &mut Runtime, _instruction: Instruction) -> Result<(), ErrorKind> {
+    for i in 0..AUDIO_PATTERN_SIZE {
+        runtime.audio_pattern[i] = runtime.storage.read(index_address(runtime, i))?;
+    }
+    runtime.pattern_loaded = true;
+    return Ok(());
+}
+
+// set the audio pattern playback pitch to vx
+fn handleFX3A(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
+    runtime.pitch = runtime.storage.variables[instruction.x];
+    return Ok(());
 }
 
 // grab bag opcodes
-pub fn handleF(runtime: &mut Runtime, instruction: Instruction) {
+pub fn handleF(runtime: &mut Runtime, instruction: Instruction) -> Result<(), ErrorKind> {
     match instruction.nn {
+        0x00 if runtime.mode == Mode::X0CHIP && instruction.x == 0 => handleF000(runtime, instruction),
+        0x01 if runtime.mode == Mode::X0CHIP => handleFN01(runtime, instruction),
+        0x02 if runtime.mode == Mode::X0CHIP && instruction.x == 0 => handleF002(runtime, instruction),
         0x07 => handleFX07(runtime, instruction),
         0x0A => handleFX0A(runtime, instruction),
         0x15 => handleFX15(runtime, instruction),
         0x18 => handleFX18(runtime, instruction),
         0x1E => handleFX1E(runtime, instruction),
         0x29 => handleFX29(runtime, instruction),
+        0x30 if runtime.mode != Mode::CHIP8 => handleFX30(runtime, instruction),
         0x33 => handleFX33(runtime, instruction),
+        0x3A if runtime.mode == Mode::X0CHIP => handleFX3A(runtime, instruction),
         0x55 => handleFX55(runtime, instruction),
         0x65 => handleFX65(runtime, instruction),
+        0x75 if runtime.mode != Mode::CHIP8 => handleFX75(runtime, instruction),
+        0x85 if runtime.mode != Mode::CHIP8 => handleFX85(runtime, instruction),
         _ => handle_error_case(runtime, instruction),
     }
 }
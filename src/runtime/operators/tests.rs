@@ -0,0 +1,244 @@
+use crate::runtime::{Runtime, Mode, State};
+use crate::runtime::error::ErrorKind;
+use crate::runtime::quirks::Quirks;
+
+const START: usize = 0x200;
+
+fn load(program: &[u16], mode: Mode) -> Runtime {
+    let rom: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    return Runtime::new(&rom, mode);
+}
+
+// run the program through to its end, letting any display waits go by as if a frame had passed
+fn run(program: &[u16], mode: Mode) -> Runtime {
+    let mut runtime = load(program, mode);
+    let end = START + program.len() * 2;
+    while runtime.storage.program_counter < end && !runtime.is_halted() {
+        if runtime.waiting_for_vblank {
+            runtime.tick_timers();
+        }
+        runtime.step().expect("the program shouldn't fault");
+    }
+    return runtime;
+}
+
+// the plane bits of a row as a string, from x for count pixels
+fn row(runtime: &Runtime, y: usize, x: usize, count: usize) -> String {
+    return runtime.display.rows()[y][x..x + count].iter().map(|pixel| pixel.to_string()).collect();
+}
+
+#[test]
+fn add_sets_carry_from_256() {
+    let runtime = run(&[0x60C8, 0x6138, 0x8014], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 0);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+
+    let runtime = run(&[0x60C8, 0x6137, 0x8014], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 255);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+}
+
+#[test]
+fn subtract_with_equal_operands_doesnt_borrow() {
+    let runtime = run(&[0x6005, 0x6105, 0x8015], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 0);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+
+    let runtime = run(&[0x6005, 0x6105, 0x8017], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 0);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+}
+
+#[test]
+fn subtract_borrows_and_wraps() {
+    let runtime = run(&[0x6004, 0x6105, 0x8015], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 255);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+
+    let runtime = run(&[0x6005, 0x6104, 0x8017], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 255);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+}
+
+#[test]
+fn flag_wins_when_vf_is_the_target() {
+    let runtime = run(&[0x6FC8, 0x6138, 0x8F14], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+
+    let runtime = run(&[0x6F03, 0x8FF6], Mode::SCHIP);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+}
+
+#[test]
+fn shifts_follow_the_shifting_quirk() {
+    // chip-8 shifts a copy of vy, schip shifts vx in place
+    let runtime = run(&[0x6081, 0x6103, 0x801E], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0x0], 6);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+
+    let runtime = run(&[0x6081, 0x6103, 0x801E], Mode::SCHIP);
+    assert_eq!(runtime.storage.variables[0x0], 2);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+}
+
+#[test]
+fn logic_resets_vf_only_with_the_quirk() {
+    let runtime = run(&[0x6F05, 0x8011], Mode::CHIP8);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+
+    let runtime = run(&[0x6F05, 0x8011], Mode::SCHIP);
+    assert_eq!(runtime.storage.variables[0xF], 5);
+}
+
+#[test]
+fn index_overflow_sets_vf_outside_xo_chip() {
+    let runtime = run(&[0xAFFF, 0x6001, 0xF01E], Mode::CHIP8);
+    assert_eq!(runtime.storage.index_register, 0x1000);
+    assert_eq!(runtime.storage.variables[0xF], 1);
+
+    let runtime = run(&[0xF000, 0x1000, 0x6001, 0xF01E], Mode::X0CHIP);
+    assert_eq!(runtime.storage.index_register, 0x1001);
+    assert_eq!(runtime.storage.variables[0xF], 0);
+}
+
+#[test]
+fn xo_chip_index_wraps_at_16_bits() {
+    // i := long 0xFFFE, save v3
+    let runtime = run(&[0xF000, 0xFFFE, 0x6001, 0x6102, 0x6203, 0x6304, 0xF355], Mode::X0CHIP);
+    assert_eq!(&runtime.storage.memory[0xFFFE..], &[1, 2]);
+    assert_eq!(&runtime.storage.memory[..2], &[3, 4]);
+    assert_eq!(runtime.storage.index_register, 0x0002);
+}
+
+#[test]
+fn drawing_twice_erases_and_collides() {
+    // the 0 glyph at the top left, then again
+    let runtime = run(&[0x6000, 0xF029, 0xD005], Mode::CHIP8);
+    assert_eq!(row(&runtime, 0, 0, 8), "11110000");
+    assert_eq!(row(&runtime, 1, 0, 8), "10010000");
+    assert_eq!(runtime.storage.variables[0xF], 0);
+
+    let runtime = run(&[0x6000, 0xF029, 0xD005, 0xD005], Mode::CHIP8);
+    assert!(runtime.display.rows().iter().all(|row| row.iter().all(|&pixel| pixel == 0)));
+    assert_eq!(runtime.storage.variables[0xF], 1);
+}
+
+#[test]
+fn sprites_clip_at_the_edge_with_the_quirk() {
+    // the 0 glyph at x 62, y 30
+    let runtime = run(&[0x6000, 0xF029, 0x6A3E, 0x6B1E, 0xDAB5], Mode::CHIP8);
+    assert_eq!(row(&runtime, 30, 60, 4), "0011");
+    assert_eq!(row(&runtime, 30, 0, 2), "00");
+    assert!(runtime.display.rows()[0].iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn sprites_wrap_round_without_the_quirk() {
+    let runtime = run(&[0x6000, 0xF029, 0x6A3E, 0x6B1E, 0xDAB5], Mode::X0CHIP);
+    assert_eq!(row(&runtime, 30, 60, 4), "0011");
+    assert_eq!(row(&runtime, 30, 0, 2), "11");
+    // the last three rows come round to the top
+    assert_eq!(row(&runtime, 2, 62, 2), "11");
+}
+
+#[test]
+fn sprite_positions_wrap_before_drawing() {
+    // x 70 is x 6 on a 64 wide screen
+    let runtime = run(&[0x6000, 0xF029, 0x6A46, 0x6B00, 0xDAB1], Mode::CHIP8);
+    assert_eq!(row(&runtime, 0, 4, 8), "00111100");
+}
+
+#[test]
+fn key_wait_finishes_on_release() {
+    let mut runtime = load(&[0xF30A], Mode::CHIP8);
+    runtime.step().unwrap();
+    assert_eq!(runtime.storage.program_counter, START);
+
+    runtime.set_key(0x5, true);
+    runtime.step().unwrap();
+    runtime.step().unwrap();
+    assert_eq!(runtime.storage.program_counter, START);
+
+    runtime.set_key(0x5, false);
+    runtime.step().unwrap();
+    assert_eq!(runtime.storage.program_counter, START + 2);
+    assert_eq!(runtime.storage.variables[0x3], 0x5);
+}
+
+#[test]
+fn key_wait_catches_a_tap_within_a_frame() {
+    let mut runtime = load(&[0xF30A], Mode::CHIP8);
+    runtime.set_key(0xA, true);
+    runtime.set_key(0xA, false);
+    runtime.step().unwrap();
+    runtime.step().unwrap();
+    assert_eq!(runtime.storage.variables[0x3], 0xA);
+}
+
+#[test]
+fn exit_stops_the_machine_in_schip() {
+    let mut runtime = run(&[0x00FD, 0x6001], Mode::SCHIP);
+    assert_eq!(runtime.state, State::Exited);
+    assert_eq!(runtime.storage.program_counter, START + 2);
+    runtime.step().unwrap();
+    assert_eq!(runtime.storage.variables[0x0], 0);
+}
+
+#[test]
+fn exit_is_a_machine_routine_in_chip8() {
+    let mut runtime = load(&[0x00FD], Mode::CHIP8);
+    let error = runtime.step().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::MachineRoutine);
+    assert_eq!(runtime.storage.program_counter, START);
+}
+
+#[test]
+fn schip_scrolls_down_and_sideways() {
+    // the 0 glyph at 8, 0
+    let draw: [u16; 4] = [0x6000, 0xF029, 0x6A08, 0xDA05];
+
+    let runtime = run(&[&draw[..], &[0x00C2]].concat(), Mode::SCHIP);
+    assert_eq!(row(&runtime, 0, 8, 4), "0000");
+    assert_eq!(row(&runtime, 2, 8, 4), "1111");
+
+    let runtime = run(&[&draw[..], &[0x00FB]].concat(), Mode::SCHIP);
+    assert_eq!(row(&runtime, 0, 8, 8), "00001111");
+
+    let runtime = run(&[&draw[..], &[0x00FC]].concat(), Mode::SCHIP);
+    assert_eq!(row(&runtime, 0, 4, 4), "1111");
+}
+
+#[test]
+fn xo_chip_scrolls_up_the_selected_planes() {
+    // the 0 glyph at 0, 4 on the first plane, then the first plane scrolled up 4
+    let runtime = run(&[0x6000, 0xF029, 0x6B04, 0xD0B5, 0x00D4], Mode::X0CHIP);
+    assert_eq!(row(&runtime, 0, 0, 4), "1111");
+    assert_eq!(row(&runtime, 1, 0, 4), "1001");
+    assert_eq!(row(&runtime, 5, 0, 4), "0000");
+
+    // only the second plane selected, the first stays put
+    let runtime = run(&[0x6000, 0xF029, 0x6B04, 0xD0B5, 0xF201, 0x00D4], Mode::X0CHIP);
+    assert_eq!(row(&runtime, 0, 0, 4), "0000");
+    assert_eq!(row(&runtime, 4, 0, 4), "1111");
+}
+
+#[test]
+fn scrolling_is_a_machine_routine_in_chip8() {
+    let mut runtime = load(&[0x00C1], Mode::CHIP8);
+    assert_eq!(runtime.step().unwrap_err().kind, ErrorKind::MachineRoutine);
+
+    let mut runtime = load(&[0x00D1], Mode::SCHIP);
+    assert_eq!(runtime.step().unwrap_err().kind, ErrorKind::MachineRoutine);
+}
+
+#[test]
+fn display_wait_only_holds_up_lores() {
+    let mut runtime = load(&[0xD005, 0x00FF, 0xD005], Mode::SCHIP);
+    runtime.quirks = Quirks::SCHIP_1_1;
+    runtime.step().unwrap();
+    assert!(runtime.waiting_for_vblank);
+    runtime.tick_timers();
+    runtime.step().unwrap();
+    runtime.step().unwrap();
+    assert!(!runtime.waiting_for_vblank);
+}
@@ -0,0 +1,119 @@
+use crate::runtime::Mode;
+
+// the behaviours that differ between chip-8 implementations, independent of which instructions exist
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Quirks {
+    pub vf_reset: bool, // 8xy1, 8xy2 and 8xy3 clear vf
+    pub shifting: bool, // 8xy6 and 8xye shift vx in place instead of copying vy first
+    pub jumping: bool, // bxnn jumps to xnn + vx instead of nnn + v0
+    pub memory_increment: bool, // fx55 and fx65 leave i pointing past the last register
+    pub clipping: bool, // sprites are cut off at the screen edges instead of wrapping around
+    pub display_wait: bool, // dxyn waits for the next 60hz tick before the program carries on, in lores only
+}
+
+// names accepted by `preset`, in the order they're listed in the usage
+pub const PRESET_NAMES: [&str; 6] = ["chip8", "chip48", "schip1.0", "schip1.1", "schip", "xochip"];
+pub const QUIRK_NAMES: [&str; 6] = ["vf_reset", "shifting", "jumping", "memory_increment", "clipping", "display_wait"];
+
+impl Quirks {
+    // the original cosmac vip interpreter
+    pub const CHIP8: Quirks = Quirks {
+        vf_reset: true,
+        shifting: false,
+        jumping: false,
+        memory_increment: true,
+        clipping: true,
+        display_wait: true,
+    };
+
+    // the hp48 port, which schip 1.0 kept
+    pub const CHIP48: Quirks = Quirks {
+        vf_reset: false,
+        shifting: true,
+        jumping: true,
+        memory_increment: true,
+        clipping: true,
+        display_wait: false,
+    };
+
+    pub const SCHIP_1_0: Quirks = Quirks::CHIP48;
+
+    // schip 1.1 stopped moving i on fx55/fx65 and waits for the display in lores
+    pub const SCHIP_1_1: Quirks = Quirks {
+        vf_reset: false,
+        shifting: true,
+        jumping: true,
+        memory_increment: false,
+        clipping: true,
+        display_wait: true,
+    };
+
+    // schip as octo and most modern interpreters run it
+    pub const SCHIP_MODERN: Quirks = Quirks {
+        vf_reset: false,
+        shifting: true,
+        jumping: true,
+        memory_increment: false,
+        clipping: true,
+        display_wait: false,
+    };
+
+    pub const XOCHIP: Quirks = Quirks {
+        vf_reset: false,
+        shifting: false,
+        jumping: false,
+        memory_increment: true,
+        clipping: false,
+        display_wait: false,
+    };
+
+    // what each mode behaves like unless told otherwise
+    pub fn for_mode(mode: Mode) -> Quirks {
+        match mode {
+            Mode::CHIP8 => Quirks::CHIP8,
+            Mode::SCHIP => Quirks::SCHIP_MODERN,
+            Mode::X0CHIP => Quirks::XOCHIP,
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name {
+            "chip8" => Some(Quirks::CHIP8),
+            "chip48" => Some(Quirks::CHIP48),
+            "schip1.0" => Some(Quirks::SCHIP_1_0),
+            "schip1.1" => Some(Quirks::SCHIP_1_1),
+            "schip" => Some(Quirks::SCHIP_MODERN),
+            "xochip" => Some(Quirks::XOCHIP),
+            _ => None,
+        }
+    }
+
+    // a single quirk by name, none if there's no quirk called that
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut quirks = *self;
+        let value: &mut bool = quirks.field(name)?;
+        return Some(*value);
+    }
+
+    // logic is what other emulators' quirk lists call vf_reset
+    fn field(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "vf_reset" | "logic" => Some(&mut self.vf_reset),
+            "shifting" => Some(&mut self.shifting),
+            "jumping" => Some(&mut self.jumping),
+            "memory_increment" => Some(&mut self.memory_increment),
+            "clipping" => Some(&mut self.clipping),
+            "display_wait" => Some(&mut self.display_wait),
+            _ => None,
+        }
+    }
+
+    // flip a single quirk by name, false if there's no quirk called that
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        let Some(quirk) = self.field(name) else {
+            return false;
+        };
+        *quirk = value;
+        return true;
+    }
+}
@@ -0,0 +1,175 @@
+// where cxnn gets its numbers. every kind starts over from a seed, so a run can be repeated
+#[derive(Debug, Clone, PartialEq)]
+pub enum Random {
+    Xorshift(u64),
+    // modelled on the cosmac vip interpreter, which had no generator as such: it added up bytes of its own code,
+    // picked by a pointer that the frame interrupt also moves along, so the numbers depend on timing
+    Vip { pointer: u8, last: u8 },
+    Fixed { sequence: Vec<u8>, position: usize }, // round and round a known list, for tests
+}
+
+pub const RANDOM_NAMES: [&str; 3] = ["xorshift", "vip", "fixed"];
+
+const VIP_INTERPRETER_PAGE: usize = 0x000; // where our font and scratch space sit, standing in for the vip's code
+
+// spreads a seed out so small and zero seeds still give a good xorshift state
+fn splitmix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return (z ^ (z >> 31)).max(1);
+}
+
+impl Random {
+    // the usual kind, xorshift
+    pub fn seeded(seed: u64) -> Random {
+        return Random::Xorshift(splitmix(seed));
+    }
+
+    // "xorshift", "vip", "fixed" counting 0 to 255, or "fixed:1,2,0x30" for a list of bytes
+    pub fn from_name(name: &str, seed: u64) -> Option<Random> {
+        let mut random = match name.split_once(':') {
+            None if name == "xorshift" => Random::Xorshift(0),
+            None if name == "vip" => Random::Vip { pointer: 0, last: 0 },
+            None if name == "fixed" => Random::Fixed { sequence: (0..=255).collect(), position: 0 },
+            Some(("fixed", list)) => {
+                let sequence = list.split(',').map(|byte| {
+                    let byte = byte.trim();
+                    match byte.strip_prefix("0x") {
+                        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+                        None => byte.parse().ok(),
+                    }
+                }).collect::<Option<Vec<u8>>>()?;
+                Random::Fixed { sequence, position: 0 }
+            },
+            _ => return None,
+        };
+        random.reseed(seed);
+        return Some(random);
+    }
+
+    // the kind of generator as from_name takes it, leaving out where it's got to
+    pub fn name(&self) -> String {
+        match self {
+            Random::Xorshift(_) => String::from("xorshift"),
+            Random::Vip { .. } => String::from("vip"),
+            Random::Fixed { sequence, .. } if sequence.iter().copied().eq(0..=255) => String::from("fixed"),
+            Random::Fixed { sequence, .. } => {
+                let bytes: Vec<String> = sequence.iter().map(|byte| byte.to_string()).collect();
+                format!("fixed:{}", bytes.join(","))
+            },
+        }
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        match self {
+            Random::Xorshift(state) => *state = splitmix(seed),
+            Random::Vip { pointer, last } => {
+                *pointer = seed as u8;
+                *last = (seed >> 8) as u8;
+            },
+            Random::Fixed { position, .. } => *position = 0, // the list is the same whatever the seed
+        }
+    }
+
+    // the next byte, the vip kind reads it out of memory
+    pub fn next(&mut self, memory: &[usize]) -> u8 {
+        match self {
+            Random::Xorshift(state) => {
+                *state ^= *state << 13;
+                *state ^= *state >> 7;
+                *state ^= *state << 17;
+                return (*state >> 56) as u8;
+            },
+            Random::Vip { pointer, last } => {
+                *pointer = pointer.wrapping_add(1);
+                let byte = memory[VIP_INTERPRETER_PAGE + *pointer as usize] as u8;
+                *last = last.wrapping_add(byte).wrapping_add(*pointer).rotate_right(1);
+                return *last;
+            },
+            Random::Fixed { sequence, position } => {
+                let byte = sequence[*position];
+                *position = (*position + 1) % sequence.len();
+                return byte;
+            },
+        }
+    }
+
+    // once a frame, along with the timers
+    pub fn vblank(&mut self) {
+        if let Random::Vip { pointer, .. } = self {
+            *pointer = pointer.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Runtime, Mode};
+
+    fn draw(random: &mut Random, count: usize) -> Vec<u8> {
+        let memory: Vec<usize> = (0..256).map(|byte| byte * 7 % 256).collect();
+        return (0..count).map(|_| random.next(&memory)).collect();
+    }
+
+    #[test]
+    fn fixed_lists_are_parsed() {
+        let mut random = Random::from_name("fixed:1,2,0x30", 99).unwrap();
+        assert_eq!(random, Random::Fixed { sequence: vec![1, 2, 0x30], position: 0 });
+        assert_eq!(draw(&mut random, 5), vec![1, 2, 0x30, 1, 2]);
+        assert_eq!(random.name(), "fixed:1,2,48");
+        assert_eq!(Random::from_name(" fixed", 0), None);
+        assert_eq!(Random::from_name("fixed: 7 , 0xff ", 0).unwrap().name(), "fixed:7,255");
+
+        let mut counting = Random::from_name("fixed", 0).unwrap();
+        assert_eq!(draw(&mut counting, 257)[254..], [254, 255, 0]);
+        assert_eq!(counting.name(), "fixed");
+    }
+
+    #[test]
+    fn bad_fixed_lists_are_rejected() {
+        for name in ["fixed:", "fixed:1,,2", "fixed:256", "fixed:-1", "fixed:0x", "fixed:0x100", "fixed:two", "steady:1", "vip:1"] {
+            assert_eq!(Random::from_name(name, 0), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn reseeding_repeats_the_numbers() {
+        for name in ["xorshift", "vip", "fixed:3,1,4"] {
+            let mut random = Random::from_name(name, 7).unwrap();
+            let first = draw(&mut random, 32);
+            random.reseed(7);
+            assert_eq!(draw(&mut random, 32), first, "{}", name);
+            assert_eq!(draw(&mut Random::from_name(name, 7).unwrap(), 32), first, "{}", name);
+        }
+        let mut other = Random::from_name("xorshift", 8).unwrap();
+        assert_ne!(draw(&mut other, 32), draw(&mut Random::from_name("xorshift", 7).unwrap(), 32));
+        // a zero seed still gets going
+        assert!(draw(&mut Random::seeded(0), 8).iter().any(|&byte| byte != 0));
+    }
+
+    #[test]
+    fn the_vip_pointer_moves_on_the_frame_interrupt() {
+        let mut random = Random::from_name("vip", 0x1234).unwrap();
+        assert_eq!(random, Random::Vip { pointer: 0x34, last: 0x12 });
+        random.vblank();
+        assert_eq!(random, Random::Vip { pointer: 0x35, last: 0x12 });
+        let mut xorshift = Random::seeded(1);
+        xorshift.vblank();
+        assert_eq!(xorshift, Random::seeded(1));
+
+        // so the same program draws different numbers depending on how many frames went by in between
+        let numbers = |frames: usize| -> usize {
+            let mut runtime = Runtime::new(&[0xC0, 0xFF], Mode::CHIP8);
+            runtime.random = Random::from_name("vip", 5).unwrap();
+            for _i in 0..frames {
+                runtime.tick_timers();
+            }
+            runtime.step().unwrap();
+            return runtime.storage.variables[0];
+        };
+        assert_eq!(numbers(0), numbers(0));
+        assert_ne!(numbers(0), numbers(1));
+    }
+}
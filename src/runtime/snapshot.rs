@@ -0,0 +1,480 @@
+use crate::runtime::{Runtime, Mode, State, KEYBOARD_SIZE, AUDIO_PATTERN_SIZE};
+use crate::runtime::error::{ErrorKind, ExecutionError};
+use crate::runtime::storage::{MEM_SIZE, XO_MEM_SIZE, NUM_FLAGS};
+use crate::runtime::random::Random;
+use crate::runtime::quirks::{Quirks, QUIRK_NAMES};
+use crate::runtime::timing::Timing;
+use crate::runtime::clock::{Clock, TIMER_HZ};
+
+use std::fmt;
+use std::fs;
+
+// file layout: magic, version, then every field in the order written below, big endian
+const MAGIC: &[u8; 4] = b"C8SS";
+pub const SNAPSHOT_VERSION: u16 = 1;
+const NO_KEY: u8 = 0xFF;
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    Invalid(&'static str),
+    Io(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a save state"),
+            SnapshotError::UnsupportedVersion(version) => write!(f, "unsupported save state version {}", version),
+            SnapshotError::Truncated => write!(f, "save state is truncated"),
+            SnapshotError::Invalid(what) => write!(f, "save state has an invalid {}", what),
+            SnapshotError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: usize) {
+        self.bytes.push(value as u8);
+    }
+
+    fn u16(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u16).to_be_bytes());
+    }
+
+    fn u32(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.position + length > self.bytes.len() {
+            return Err(SnapshotError::Truncated);
+        }
+        let slice = &self.bytes[self.position .. self.position + length];
+        self.position += length;
+        return Ok(slice);
+    }
+
+    fn u8(&mut self) -> Result<usize, SnapshotError> {
+        return Ok(self.take(1)?[0] as usize);
+    }
+
+    fn u16(&mut self) -> Result<usize, SnapshotError> {
+        let bytes = self.take(2)?;
+        return Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize);
+    }
+
+    fn u32(&mut self) -> Result<usize, SnapshotError> {
+        let bytes = self.take(4)?;
+        return Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize);
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let bytes = self.take(8)?;
+        return Ok(u64::from_be_bytes(bytes.try_into().expect("took 8 bytes")));
+    }
+}
+
+fn mode_to_byte(mode: Mode) -> usize {
+    match mode {
+        Mode::CHIP8 => 0,
+        Mode::SCHIP => 1,
+        Mode::X0CHIP => 2,
+    }
+}
+
+fn mode_from_byte(byte: usize) -> Result<Mode, SnapshotError> {
+    match byte {
+        0 => Ok(Mode::CHIP8),
+        1 => Ok(Mode::SCHIP),
+        2 => Ok(Mode::X0CHIP),
+        _ => Err(SnapshotError::Invalid("mode")),
+    }
+}
+
+fn write_error_kind(writer: &mut Writer, kind: ErrorKind) {
+    let (code, address) = match kind {
+        ErrorKind::UnknownOpcode => (0, 0),
+        ErrorKind::MachineRoutine => (1, 0),
+        ErrorKind::EscapedProgram => (2, 0),
+        ErrorKind::StackOverflow => (3, 0),
+        ErrorKind::StackUnderflow => (4, 0),
+        ErrorKind::ProgramCounterOutOfBounds => (5, 0),
+        ErrorKind::MemoryOutOfBounds(address) => (6, address),
+    };
+    writer.u8(code);
+    writer.u32(address);
+}
+
+fn read_error_kind(reader: &mut Reader) -> Result<ErrorKind, SnapshotError> {
+    let code = reader.u8()?;
+    let address = reader.u32()?;
+    match code {
+        0 => Ok(ErrorKind::UnknownOpcode),
+        1 => Ok(ErrorKind::MachineRoutine),
+        2 => Ok(ErrorKind::EscapedProgram),
+        3 => Ok(ErrorKind::StackOverflow),
+        4 => Ok(ErrorKind::StackUnderflow),
+        5 => Ok(ErrorKind::ProgramCounterOutOfBounds),
+        6 => Ok(ErrorKind::MemoryOutOfBounds(address)),
+        _ => Err(SnapshotError::Invalid("error kind")),
+    }
+}
+
+fn write_random(writer: &mut Writer, random: &Random) {
+    match random {
+        Random::Xorshift(state) => {
+            writer.u8(0);
+            writer.u64(*state);
+        },
+        Random::Vip { pointer, last } => {
+            writer.u8(1);
+            writer.u8(*pointer as usize);
+            writer.u8(*last as usize);
+        },
+        Random::Fixed { sequence, position } => {
+            writer.u8(2);
+            writer.u16(sequence.len());
+            sequence.iter().for_each(|&byte| writer.u8(byte as usize));
+            writer.u16(*position);
+        },
+    }
+}
+
+fn read_random(reader: &mut Reader) -> Result<Random, SnapshotError> {
+    match reader.u8()? {
+        0 => match reader.u64()? {
+            0 => Err(SnapshotError::Invalid("xorshift state")), // it would only ever give zeroes
+            state => Ok(Random::Xorshift(state)),
+        },
+        1 => Ok(Random::Vip { pointer: reader.u8()? as u8, last: reader.u8()? as u8 }),
+        2 => {
+            let length = reader.u16()?;
+            let sequence = reader.take(length)?.to_vec();
+            let position = reader.u16()?;
+            if position >= length {
+                return Err(SnapshotError::Invalid("random sequence"));
+            }
+            Ok(Random::Fixed { sequence, position })
+        },
+        _ => Err(SnapshotError::Invalid("random number generator")),
+    }
+}
+
+impl Runtime {
+    // the whole machine as a versioned blob, key state is left out since the front end owns it
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = Writer { bytes: Vec::new() };
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u16(SNAPSHOT_VERSION as usize);
+
+        writer.u8(mode_to_byte(self.mode));
+        QUIRK_NAMES.iter().for_each(|name| writer.u8((self.quirks.get(name) == Some(true)) as usize));
+        writer.u8(match self.timing {
+            Timing::Instructions => 0,
+            Timing::Vip => 1,
+        });
+        writer.u32(self.clock.ips);
+        writer.u8(self.clock.frame);
+        match self.state {
+            State::Running => writer.u8(0),
+            State::Exited => writer.u8(1),
+            State::Faulted(error) => {
+                writer.u8(2);
+                writer.u32(error.pc);
+                writer.u16(error.opcode);
+                write_error_kind(&mut writer, error.kind);
+            },
+        }
+
+        let storage = &self.storage;
+        writer.u32(storage.memory.len());
+        storage.memory.iter().for_each(|&byte| writer.u8(byte));
+        writer.u32(storage.program_counter);
+        writer.u32(storage.index_register);
+        writer.u8(storage.stack.len());
+        storage.stack.iter().for_each(|&address| writer.u32(address));
+        storage.variables.iter().for_each(|&value| writer.u8(value));
+        storage.flags.iter().for_each(|&value| writer.u8(value));
+
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        self.audio_pattern.iter().for_each(|&byte| writer.u8(byte));
+        writer.u8(self.pitch);
+        writer.u8(self.current_key_press.unwrap_or(NO_KEY as usize));
+
+        writer.u16(self.display.width);
+        writer.u16(self.display.height);
+        writer.u8(self.display.selected_planes);
+        for row in self.display.rows() {
+            row.iter().for_each(|&pixel| writer.u8(pixel as usize));
+        }
+
+        writer.u64(self.seed);
+        write_random(&mut writer, &self.random);
+        writer.u8(self.pattern_loaded as usize);
+
+        return writer.bytes;
+    }
+
+    // nothing is touched unless the whole snapshot reads back cleanly
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != SNAPSHOT_VERSION as usize {
+            return Err(SnapshotError::UnsupportedVersion(version as u16));
+        }
+
+        let mode = mode_from_byte(reader.u8()?)?;
+        let mut quirks = Quirks::for_mode(mode);
+        for name in QUIRK_NAMES {
+            match reader.u8()? {
+                0 => quirks.set(name, false),
+                1 => quirks.set(name, true),
+                _ => return Err(SnapshotError::Invalid("quirk")),
+            };
+        }
+        let timing = match reader.u8()? {
+            0 => Timing::Instructions,
+            1 => Timing::Vip,
+            _ => return Err(SnapshotError::Invalid("timing")),
+        };
+        let ips = reader.u32()?;
+        let frame = reader.u8()?;
+        if ips == 0 || frame >= TIMER_HZ {
+            return Err(SnapshotError::Invalid("clock"));
+        }
+        let state = match reader.u8()? {
+            0 => State::Running,
+            1 => State::Exited,
+            2 => {
+                let pc = reader.u32()?;
+                let opcode = reader.u16()?;
+                let kind = read_error_kind(&mut reader)?;
+                State::Faulted(ExecutionError { pc, opcode, kind })
+            },
+            _ => return Err(SnapshotError::Invalid("state")),
+        };
+
+        let memory_size = reader.u32()?;
+        if memory_size != MEM_SIZE && memory_size != XO_MEM_SIZE {
+            return Err(SnapshotError::Invalid("memory size"));
+        }
+        let memory: Vec<usize> = reader.take(memory_size)?.iter().map(|&byte| byte as usize).collect();
+        let program_counter = reader.u32()?;
+        let index_register = reader.u32()?;
+        let stack_height = reader.u8()?;
+        if stack_height != self.storage.stack.len() {
+            return Err(SnapshotError::Invalid("stack height"));
+        }
+        let mut stack = self.storage.stack;
+        for slot in stack.iter_mut() {
+            *slot = reader.u32()?;
+        }
+        let mut variables = self.storage.variables;
+        for value in variables.iter_mut() {
+            *value = reader.u8()?;
+        }
+        let mut flags = [0; NUM_FLAGS];
+        for value in flags.iter_mut() {
+            *value = reader.u8()?;
+        }
+
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let mut audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        for byte in audio_pattern.iter_mut() {
+            *byte = reader.u8()?;
+        }
+        let pitch = reader.u8()?;
+        let current_key_press = match reader.u8()? {
+            key if key == NO_KEY as usize => None,
+            key if key < KEYBOARD_SIZE => Some(key),
+            _ => return Err(SnapshotError::Invalid("pending key press")),
+        };
+
+        let width = reader.u16()?;
+        let height = reader.u16()?;
+        let selected_planes = reader.u8()?;
+        if width == 0 || height == 0 {
+            return Err(SnapshotError::Invalid("display size"));
+        }
+        let mut rows: Vec<Vec<u8>> = Vec::with_capacity(height);
+        for _y in 0..height {
+            rows.push(reader.take(width)?.to_vec());
+        }
+        let seed = reader.u64()?;
+        let random = read_random(&mut reader)?;
+        let pattern_loaded = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SnapshotError::Invalid("audio pattern flag")),
+        };
+
+        self.mode = mode;
+        self.quirks = quirks;
+        self.timing = timing;
+        self.clock = Clock { ips, frame };
+        self.state = state;
+        self.storage.memory = memory;
+        self.storage.program_counter = program_counter;
+        self.storage.index_register = index_register;
+        self.storage.stack = stack;
+        self.storage.variables = variables;
+        self.storage.flags = flags;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.audio_pattern = audio_pattern;
+        self.pattern_loaded = pattern_loaded;
+        self.pitch = pitch;
+        self.current_key_press = current_key_press;
+        self.display.restore(rows, selected_planes);
+        self.seed = seed;
+        self.random = random;
+        return Ok(());
+    }
+
+    pub fn save_state_file(&self, path: &str) -> Result<(), SnapshotError> {
+        return fs::write(path, self.save_state())
+            .map_err(|error| SnapshotError::Io(format!("could not write {}: {}", path, error)));
+    }
+
+    pub fn load_state_file(&mut self, path: &str) -> Result<(), SnapshotError> {
+        let bytes = fs::read(path)
+            .map_err(|error| SnapshotError::Io(format!("could not read {}: {}", path, error)))?;
+        return self.load_state(&bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a machine that's been doing things: drawn on both planes, called a subroutine, set its timers and loaded a pattern
+    fn busy_runtime() -> Runtime {
+        let program: [u16; 12] = [
+            0x6005, 0xF015, 0xF118, // delay and buzzer
+            0xF301, 0xF029, 0xD015, // a glyph on both planes
+            0xC0FF, // a random number
+            0xF002, // an audio pattern from the font
+            0x2214, // call the routine below
+            0x1212, // and wait
+            0x6107, 0x00EE,
+        ];
+        let rom: Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+        let mut runtime = Runtime::new(&rom, Mode::X0CHIP);
+        runtime.reseed(1234);
+        for _i in 0..12 {
+            runtime.step().unwrap();
+        }
+        return runtime;
+    }
+
+    #[test]
+    fn save_load_save_is_the_same() {
+        let runtime = busy_runtime();
+        let bytes = runtime.save_state();
+        let mut loaded = Runtime::new(&[], Mode::CHIP8);
+        loaded.load_state(&bytes).unwrap();
+        assert_eq!(loaded.save_state(), bytes);
+        assert_eq!(loaded.mode, Mode::X0CHIP);
+        assert!(loaded.pattern_loaded);
+    }
+
+    #[test]
+    fn a_loaded_state_runs_on_the_same() {
+        let mut runtime = busy_runtime();
+        let mut loaded = Runtime::new(&[], Mode::X0CHIP);
+        loaded.load_state(&runtime.save_state()).unwrap();
+        for _i in 0..20 {
+            runtime.step().unwrap();
+            loaded.step().unwrap();
+        }
+        runtime.tick_timers();
+        loaded.tick_timers();
+        assert_eq!(loaded.save_state(), runtime.save_state());
+    }
+
+    #[test]
+    fn the_mode_brings_its_quirks_and_clock() {
+        let mut runtime = busy_runtime();
+        runtime.quirks.clipping = true;
+        runtime.timing = Timing::Vip;
+        runtime.clock = Clock::new(1000);
+        runtime.clock.next_frame();
+        let mut loaded = Runtime::new(&[], Mode::CHIP8);
+        loaded.load_state(&runtime.save_state()).unwrap();
+        assert_eq!(loaded.mode, Mode::X0CHIP);
+        assert_eq!(loaded.quirks, Quirks { clipping: true, ..Quirks::XOCHIP });
+        assert_eq!(loaded.timing, Timing::Vip);
+        assert_eq!(loaded.clock, runtime.clock);
+
+        // and logic carries on the xo-chip way, leaving vf alone where chip-8 would clear it
+        runtime.storage.variables[0xF] = 5;
+        runtime.storage.memory[0x212..0x214].copy_from_slice(&[0x80, 0x11]);
+        loaded.load_state(&runtime.save_state()).unwrap();
+        loaded.step().unwrap();
+        assert_eq!(loaded.storage.variables[0xF], 5);
+    }
+
+    #[test]
+    fn faults_and_other_generators_round_trip() {
+        let mut runtime = Runtime::new(&[0x00, 0x00], Mode::CHIP8);
+        runtime.random = Random::from_name("fixed:1,2,3", 0).unwrap();
+        runtime.step().unwrap_err();
+        let bytes = runtime.save_state();
+        let mut loaded = Runtime::new(&[], Mode::CHIP8);
+        loaded.load_state(&bytes).unwrap();
+        assert_eq!(loaded.state, runtime.state);
+        assert_eq!(loaded.save_state(), bytes);
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut bytes = busy_runtime().save_state();
+        bytes[0] = b'X';
+        assert_eq!(Runtime::new(&[], Mode::CHIP8).load_state(&bytes), Err(SnapshotError::BadMagic));
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let mut bytes = busy_runtime().save_state();
+        for version in [0, SNAPSHOT_VERSION + 1] {
+            bytes[4..6].copy_from_slice(&version.to_be_bytes());
+            assert_eq!(Runtime::new(&[], Mode::CHIP8).load_state(&bytes), Err(SnapshotError::UnsupportedVersion(version)));
+        }
+    }
+
+    #[test]
+    fn truncated_states_are_rejected_and_change_nothing() {
+        let bytes = busy_runtime().save_state();
+        let mut runtime = Runtime::new(&[0x12, 0x00], Mode::CHIP8);
+        let before = runtime.save_state();
+        for length in [0, 3, 5, 100, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(runtime.load_state(&bytes[..length]), Err(SnapshotError::Truncated), "{} bytes", length);
+        }
+        assert_eq!(runtime.save_state(), before);
+    }
+}
@@ -1,10 +1,29 @@
 #[derive(Debug)]
 pub struct Instruction {
     // variable size is handled custom
+    pub raw: usize,
     pub identifier: usize,
     pub x: usize,
     pub y: usize,
     pub n: usize,
     pub nn: usize,
     pub nnn: usize,
+}
+
+const BYTE_LENGTH: usize = 8;
+const HALF_BYTE: usize = 4;
+
+impl Instruction {
+    // split a raw two byte opcode into its nibbles
+    pub fn decode(raw_instruction: usize) -> Instruction {
+        return Instruction {
+            raw: raw_instruction,
+            identifier: (raw_instruction & 0xF000) >> (BYTE_LENGTH + HALF_BYTE),
+            x: (raw_instruction & 0x0F00) >> BYTE_LENGTH,
+            y: (raw_instruction & 0x00F0) >> HALF_BYTE,
+            n: (raw_instruction & 0x000F),
+            nn: (raw_instruction & 0x00FF),
+            nnn: raw_instruction & 0x0FFF,
+        };
+    }
 }
\ No newline at end of file
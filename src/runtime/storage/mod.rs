@@ -1,25 +1,28 @@
 pub mod instruction;
 mod font;
 use instruction::Instruction;
-use font::{FONT, FONT_SPRITE_LENGTH};
-use std::fs;
-use std::path::Path;
+use crate::runtime::error::ErrorKind;
+use font::{FONT, FONT_SPRITE_LENGTH, BIG_FONT, BIG_FONT_SPRITE_LENGTH};
 
-const MEM_SIZE: usize = 4096;
+pub const MEM_SIZE: usize = 4096;
+pub const XO_MEM_SIZE: usize = 65536; // x0-chip addresses a full 16 bits
 const STACK_HEIGHT: usize = 16;
 const NUM_VARS: usize = 16;
-const START_SLOT: usize = 0x0200;
+pub const SCHIP_NUM_FLAGS: usize = 8; // schip rpl user flags
+pub const NUM_FLAGS: usize = 16; // x0-chip doubles them
+pub const START_SLOT: usize = 0x0200;
 const FONT_START: usize = 0x0050;
+const BIG_FONT_START: usize = FONT_START + FONT.len();
 const BYTE_LENGTH: usize = 8;
-const HALF_BYTE: usize = 4;
 
 pub struct Storage {
     // all the var size limits have custom implementations
-    pub memory: [usize; MEM_SIZE],
+    pub memory: Vec<usize>,
     pub program_counter: usize,
     pub index_register: usize,
     pub stack: [usize; STACK_HEIGHT],
     pub variables: [usize; NUM_VARS],
+    pub flags: [usize; NUM_FLAGS],
 }
 
 /*
@@ -38,33 +41,24 @@ fn main() {
  */
 
 impl Storage {
-    pub fn initialize(file_name: String) -> Storage{
+    pub fn initialize(program: &[u8], memory_size: usize) -> Storage{
         let mut storage: Storage = Storage {
-            memory: [0; MEM_SIZE],
+            memory: vec![0; memory_size],
             program_counter: START_SLOT, // start of the program
             index_register: 0,
             stack: [0; STACK_HEIGHT],
             variables: [0; NUM_VARS],
+            flags: [0; NUM_FLAGS],
         };
         storage.load_font();
-        storage.load_program(file_name);
+        storage.load_program(program);
 
         return storage;
     }
 
-    fn load_program(&mut self, file_name: String) {
-        let string_path = format!("./{}", file_name);
-        let filepath = Path::new(&string_path);
-        assert!(filepath.exists(), "{:#?}", filepath.display());
-
-        let contents: Vec<u8> = fs::read(
-            filepath,
-        ).expect(
-            &format!("path {} to file not found", filepath.display()),
-        );
-
+    pub fn load_program(&mut self, contents: &[u8]) {
         let end_slot: usize = START_SLOT + contents.len();
-        assert!(end_slot <= MEM_SIZE, "program out of bounds");
+        assert!(end_slot <= self.memory.len(), "program out of bounds");
 
         let program_slice: &mut [usize] = &mut self.memory[START_SLOT .. end_slot];
         program_slice.iter_mut().enumerate().for_each(|(index, slot)| {
@@ -77,25 +71,50 @@ impl Storage {
         font_slice.iter_mut().enumerate().for_each(|(index, slot)| {
             *slot = FONT[index];
         });
+        let big_font_slice: &mut [usize] = &mut self.memory[BIG_FONT_START .. BIG_FONT_START + BIG_FONT.len()];
+        big_font_slice.iter_mut().enumerate().for_each(|(index, slot)| {
+            *slot = BIG_FONT[index];
+        });
     }
 
     pub fn show_memory(&self) {
         println!("{:?}", self.memory);
     }
 
-    pub fn pop_pc_from_stack(&mut self) {
-        let last_stack_element_pos = self.stack.iter().position(|&x| x == 0).unwrap() - 1;
+    // how many return addresses are on the stack
+    pub fn stack_depth(&self) -> usize {
+        return self.stack.iter().position(|&x| x == 0).unwrap_or(STACK_HEIGHT);
+    }
+
+    pub fn pop_pc_from_stack(&mut self) -> Result<(), ErrorKind> {
+        let depth = self.stack_depth();
+        if depth == 0 {
+            return Err(ErrorKind::StackUnderflow);
+        }
+        let last_stack_element_pos = depth - 1;
         let last_stack_element = self.stack[last_stack_element_pos];
         self.stack[last_stack_element_pos] = 0;
         self.program_counter = last_stack_element;
+        return Ok(());
     }
 
-    pub fn add_pc_to_stack(&mut self) {
-        let first_stack_free_pos = self.stack.iter().position(|&x| x == 0).unwrap();
+    pub fn add_pc_to_stack(&mut self) -> Result<(), ErrorKind> {
+        let first_stack_free_pos = self.stack_depth();
         if first_stack_free_pos == STACK_HEIGHT {
-            panic!("stack overflow in 2NNN");
+            return Err(ErrorKind::StackOverflow);
         }
         self.stack[first_stack_free_pos] = self.program_counter;
+        return Ok(());
+    }
+
+    pub fn read(&self, address: usize) -> Result<usize, ErrorKind> {
+        return self.memory.get(address).copied().ok_or(ErrorKind::MemoryOutOfBounds(address));
+    }
+
+    pub fn write(&mut self, address: usize, value: usize) -> Result<(), ErrorKind> {
+        let slot = self.memory.get_mut(address).ok_or(ErrorKind::MemoryOutOfBounds(address))?;
+        *slot = value;
+        return Ok(());
     }
 
     pub fn get_font_item_location(&mut self, byte: usize) -> usize {
@@ -105,21 +124,32 @@ impl Storage {
         return location;
     }
 
-    pub fn get_instruction(&mut self) -> Instruction {
+    pub fn get_big_font_item_location(&mut self, byte: usize) -> usize {
+        let char: usize = byte & 0x0F;
+        let location: usize = BIG_FONT_START + BIG_FONT_SPRITE_LENGTH * char;
+        return location;
+    }
+
+    // grab the word at address, used to peek at and consume operands
+    pub fn get_word(&self, address: usize) -> Result<usize, ErrorKind> {
+        return Ok((self.read(address)? << BYTE_LENGTH) + self.read(address + 1)?);
+    }
+
+    // skip over the next instruction, which is four bytes long when it's an x0-chip f000 nnnn
+    pub fn skip_instruction(&mut self, long_instructions: bool) {
+        if long_instructions && self.get_word(self.program_counter) == Ok(0xF000) {
+            self.program_counter += 4;
+        } else {
+            self.program_counter += 2;
+        }
+    }
+
+    pub fn get_instruction(&mut self) -> Result<Instruction, ErrorKind> {
         // grab the two bytes starting at PC and collate them
-        let raw_instruction: usize =
-            (self.memory[self.program_counter] << BYTE_LENGTH)
-            + self.memory[self.program_counter + 1];
+        let raw_instruction: usize = self.get_word(self.program_counter)
+            .map_err(|_| ErrorKind::ProgramCounterOutOfBounds)?;
         self.program_counter += 2;
-        let instruction: Instruction = Instruction {
-            identifier: (raw_instruction & 0xF000) >> (BYTE_LENGTH + HALF_BYTE),
-            x: (raw_instruction & 0x0F00) >> BYTE_LENGTH,
-            y: (raw_instruction & 0x00F0) >> HALF_BYTE,
-            n: (raw_instruction & 0x000F),
-            nn: (raw_instruction & 0x00FF),
-            nnn: raw_instruction & 0x0FFF,
-        };
-        return instruction;
+        return Ok(Instruction::decode(raw_instruction));
     }
 }
 
@@ -0,0 +1,60 @@
+use crate::runtime::storage::instruction::Instruction;
+
+// the 1802 in the vip runs at 1.7609mhz with 8 clocks to a machine cycle, 3668 machine cycles a frame
+pub const VIP_CYCLES_PER_FRAME: usize = 3668;
+const DISPLAY_DMA_CYCLES: usize = 1024; // the 1861 steals a cycle for each of 8 bytes on 128 lines
+const INTERRUPT_CYCLES: usize = 48; // the interrupt routine around the dma, which also counts the timers down
+// what's left over for the interpreter each frame
+pub const VIP_PROGRAM_CYCLES: usize = VIP_CYCLES_PER_FRAME - DISPLAY_DMA_CYCLES - INTERRUPT_CYCLES;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Timing {
+    Instructions, // every instruction costs the same, the clock sets how many run a frame
+    Vip, // instructions cost what they took in the cosmac vip interpreter and dxyn waits for vblank
+}
+
+impl Timing {
+    pub fn from_name(name: &str) -> Option<Timing> {
+        match name {
+            "instructions" => Some(Timing::Instructions),
+            "vip" => Some(Timing::Vip),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Timing::Instructions => "instructions",
+            Timing::Vip => "vip",
+        }
+    }
+}
+
+// approximate machine cycles the vip interpreter spends on an instruction, fetch and decode included.
+// sprites pay per row and the register/memory transfers per register
+pub fn vip_cycles(instruction: &Instruction) -> usize {
+    match instruction.identifier {
+        0x0 => match instruction.nnn {
+            0x0E0 => 24,
+            0x0EE => 23,
+            _ => 4,
+        },
+        0x1 | 0x2 | 0xB => 23,
+        0x3 | 0x4 | 0xA => 12,
+        0x5 | 0x9 => 16,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0xC => 36,
+        0xD => 26 + 15 * instruction.n,
+        0xE => 16,
+        0xF => match instruction.nn {
+            0x1E => 19,
+            0x29 => 20,
+            0x33 => 204,
+            0x55 | 0x65 => 14 + 14 * (instruction.x + 1),
+            _ => 10,
+        },
+        _ => 10,
+    }
+}
@@ -0,0 +1,66 @@
+// runs every program in tests/golden headless and compares the screen it ends up on with the .txt beside it.
+// a program says how it wants running in comments at the top, `# mode: 0|1|2` like the command line and `# frames: n`.
+// set GOLDEN_UPDATE=1 to write the screens out instead of comparing them, then look over the diff
+#![allow(clippy::needless_return)]
+
+use chip_8_interpreter::assembler::assemble;
+use chip_8_interpreter::frontend::audio::NullAudio;
+use chip_8_interpreter::frontend::headless::{run_frames, screen_ascii};
+use chip_8_interpreter::runtime::{Runtime, Mode};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_FRAMES: usize = 60;
+
+// the value of a `# name: value` line
+fn header<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    return source.lines()
+        .filter_map(|line| line.trim().strip_prefix('#'))
+        .filter_map(|line| line.trim().strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+        .next();
+}
+
+fn run_program(path: &Path) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    let mode = match header(&source, "mode").unwrap_or("0") {
+        "0" => Mode::CHIP8,
+        "1" => Mode::SCHIP,
+        "2" => Mode::X0CHIP,
+        other => panic!("{}: bad mode {}", path.display(), other),
+    };
+    let frames: usize = header(&source, "frames").map_or(DEFAULT_FRAMES, |frames| frames.parse().unwrap());
+    let rom = assemble(&source, mode).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+    let mut runtime = Runtime::new(&rom, mode);
+    run_frames(&mut runtime, frames, &mut NullAudio).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+    return screen_ascii(&runtime.display);
+}
+
+#[test]
+fn screens_match_the_golden_files() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    let update = env::var_os("GOLDEN_UPDATE").is_some();
+    let mut programs: Vec<PathBuf> = fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "8o"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no programs in {}", directory.display());
+
+    let mut mismatches: Vec<String> = Vec::new();
+    for program in programs {
+        let screen = run_program(&program);
+        let golden = program.with_extension("txt");
+        if update {
+            fs::write(&golden, &screen).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&golden).unwrap_or_else(|_| panic!("no {}, run with GOLDEN_UPDATE=1 to make it", golden.display()));
+        if screen != expected {
+            mismatches.push(format!("{} doesn't match {}, got:\n{}", program.display(), golden.display(), screen));
+        }
+    }
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
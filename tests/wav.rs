@@ -0,0 +1,62 @@
+// runs a program that sounds the buzzer headless into a wav and reads the file back, the wav is made from emulated
+// time so the beep comes out exactly as long as the sound timer says
+#![allow(clippy::needless_return)]
+
+use chip_8_interpreter::assembler::assemble;
+use chip_8_interpreter::frontend::Audio;
+use chip_8_interpreter::frontend::audio::{WavAudio, WAV_SAMPLE_RATE};
+use chip_8_interpreter::frontend::headless::run_frames;
+use chip_8_interpreter::frontend::sound::SoundSettings;
+use chip_8_interpreter::runtime::{Runtime, Mode};
+
+use std::env;
+use std::fs;
+
+const FRAMES: usize = 10;
+const SAMPLES_PER_FRAME: usize = 735;
+
+// the samples a program makes over some frames, written to a wav of its own so tests can run alongside each other
+fn record(name: &str, source: &str, frames: usize) -> Vec<i16> {
+    let path = env::temp_dir().join(format!("chip-8-interpreter-{}-{}.wav", name, std::process::id()));
+    let path = path.to_str().unwrap();
+    let rom = assemble(source, Mode::CHIP8).unwrap();
+    let mut runtime = Runtime::new(&rom, Mode::CHIP8);
+    let mut audio = WavAudio::create(path, &SoundSettings::default()).unwrap();
+    run_frames(&mut runtime, frames, &mut audio).unwrap();
+    audio.finish().unwrap();
+
+    let reader = hound::WavReader::open(path).unwrap();
+    assert_eq!(reader.spec().sample_rate, WAV_SAMPLE_RATE);
+    let samples: Vec<i16> = reader.into_samples().map(Result::unwrap).collect();
+    fs::remove_file(path).unwrap();
+    return samples;
+}
+
+#[test]
+fn the_buzzer_lasts_as_long_as_the_sound_timer() {
+    let samples = record("buzzer", "
+        v0 := 6
+        buzzer := v0
+        : wait jump wait
+    ", FRAMES);
+    assert_eq!(samples.len(), FRAMES * SAMPLES_PER_FRAME);
+
+    // on for the six frames the timer counts down over
+    let beep = 6 * SAMPLES_PER_FRAME;
+    assert!(samples[..beep].iter().all(|&sample| sample != 0), "silence during the beep");
+
+    // then fading out rather than cutting off, and quiet after that
+    let release = &samples[beep..];
+    let silent = release.iter().position(|&sample| sample == 0).unwrap();
+    assert!(silent > 1 && silent < SAMPLES_PER_FRAME, "released over {} samples", silent);
+    let levels: Vec<i16> = release[..silent].iter().map(|sample| sample.abs()).collect();
+    assert!(levels.windows(2).all(|pair| pair[1] <= pair[0]), "the release gets louder");
+    assert!(release[silent..].iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn nothing_is_heard_without_the_buzzer() {
+    let samples = record("silence", ": wait jump wait", FRAMES);
+    assert_eq!(samples.len(), FRAMES * SAMPLES_PER_FRAME);
+    assert!(samples.iter().all(|&sample| sample == 0));
+}